@@ -15,6 +15,26 @@ pub mod nodes;
 pub mod parser;
 
 pub mod interpreter;
+pub mod compiler;
+pub mod solver;
+pub mod tracer;
+pub mod paths;
+pub mod fitting;
+pub mod offset;
+pub mod hatch;
+pub mod layers;
+pub mod style;
+pub mod machine;
+pub mod ordering;
 pub mod renderer;
+pub mod exporter;
+pub mod svg;
+pub mod dxf;
+pub mod gcode;
+pub mod hpgl;
+pub mod page;
+pub mod lightburn;
 
 pub mod printer;
+
+pub mod benchmark;
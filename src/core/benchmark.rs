@@ -0,0 +1,188 @@
+use std::time::{Duration, Instant};
+
+use super::logger;
+use super::data;
+use super::exceptions;
+use super::interpreter;
+use super::nodes;
+use super::compiler;
+use super::run;
+
+
+
+// Number of y values sampled in each column for equations which are not explicit.
+const ROWS : i32 = 16;
+
+
+
+// Function for timing the compiled evaluator against the node tree evaluator on a script,
+// and checking that both give identical values. Returns false if they differ anywhere.
+pub fn benchmark(filename : &str) -> bool {
+    logger::info(format!("Benchmarking evaluators on file `{}`.", data::escapify(filename.to_string())));
+    let data = run::load(filename, &None);
+
+    let mut mismatches = 0;
+    for equation in data.equations.iter() {
+        let res = compare(&data, equation);
+        if ! res.success {
+            logger::critical("Compiling failed. Error provided:");
+            println!("\n{}", res.exception);
+            return false;
+        }
+        mismatches += res.mismatches;
+
+        logger::info(format!("Equation `{}`:", equation));
+        logger::info(format!(
+            "    Points : {} samples, tree {:?}, compiled {:?} ({:.1}x).",
+            res.samples, res.tree_time, res.program_time, res.tree_time.as_secs_f64() / res.program_time.as_secs_f64()
+        ));
+    }
+
+    if mismatches > 0 {
+        logger::error(format!("Evaluators gave {} different results.", mismatches));
+        return false;
+    }
+    logger::success("Evaluators gave identical results.");
+    return true;
+}
+
+
+
+// Success/Failure identification class, with the number of samples compared, how many differed,
+// and the time each evaluator took.
+#[derive(Clone, Debug)]
+pub struct ComparisonResult {
+    pub success      : bool,
+    pub samples      : usize,
+    pub mismatches   : usize,
+    pub tree_time    : Duration,
+    pub program_time : Duration,
+    pub exception    : exceptions::RendererException
+}
+
+
+
+// Function for evaluating an equation with both evaluators at points across the whole frame, and comparing every branch.
+pub fn compare(data : &interpreter::InterpreterData, equation : &nodes::Node) -> ComparisonResult {
+    let compiled = compiler::compile(equation);
+    if ! compiled.success {
+        return ComparisonResult {
+            success      : false,
+            samples      : 0,
+            mismatches   : 0,
+            tree_time    : Duration::ZERO,
+            program_time : Duration::ZERO,
+            exception    : compiled.exception
+        };
+    }
+    let program    = compiled.program;
+    let resolution = data.pixel_resolution();
+
+    // Sample points across the whole frame.
+    let mut points = vec![];
+    for pixel_x in 0..resolution.x {
+        let x = data.position.x as f64 + (data.size.x as f64 * (pixel_x as f64 / resolution.x as f64));
+        for row in 0..ROWS {
+            let y = data.position.y as f64 + (data.size.y as f64 * (row as f64 / ROWS as f64));
+            if row == 0 || ! program.explicit {
+                points.push((x, y));
+            }
+        }
+    }
+
+    let start           = Instant::now();
+    let mut tree_values = vec![];
+    for (x, y) in points.iter() {
+        tree_values.push(evaluate_tree(equation, &program, *x, *y));
+    }
+    let tree_time = start.elapsed();
+
+    let start              = Instant::now();
+    let mut stack          = vec![];
+    let mut program_values = vec![];
+    for (x, y) in points.iter() {
+        for branch in 0..program.branches() {
+            program_values.push(program.evaluate(*x, *y, branch, &mut stack));
+        }
+    }
+    let program_time = start.elapsed();
+
+    let mut mismatches = 0;
+    for (index, tree) in tree_values.iter().enumerate() {
+        for branch in 0..program.branches() {
+            let value = program_values[index * program.branches() + branch];
+            if ! same_value(tree, &program.branch(branch), value) {
+                mismatches += 1;
+            }
+        }
+    }
+
+    return ComparisonResult {
+        success      : true,
+        samples      : points.len(),
+        mismatches   : mismatches,
+        tree_time    : tree_time,
+        program_time : program_time,
+        exception    : exceptions::RendererException {
+            base    : exceptions::RendererExceptionBase::NoException,
+            message : "".to_string(),
+            range   : equation.range.clone()
+        }
+    };
+}
+
+
+
+// Evaluate with the node tree, in the same form as the compiled program.
+fn evaluate_tree(equation : &nodes::Node, program : &compiler::Program, x : f64, y : f64) -> data::MultipleValues {
+    let res = if program.explicit {
+        equation.simplify(x)
+    } else {
+        equation.simplify_at(x, Some(y))
+    };
+    let empty = data::MultipleValues {
+        values   : vec![],
+        branches : vec![]
+    };
+    if ! res.success {
+        return empty;
+    }
+    return match res.value.base {
+        nodes::NodeBase::MultipleNumber   {value}       => value,
+        nodes::NodeBase::EqualsExpression {left, right} => match (left.base, right.base) {
+            (nodes::NodeBase::MultipleNumber {value : left}, nodes::NodeBase::MultipleNumber {value : right}) => left - right,
+            _                                                                                                   => empty
+        },
+        _                                               => empty
+    };
+}
+
+
+
+// Check a compiled branch value against the node tree values. Branches the tree dropped as undefined are NaN.
+fn same_value(tree : &data::MultipleValues, branch : &data::Branch, value : f64) -> bool {
+    for (tree_value, tree_branch) in tree.values.iter().zip(tree.branches.iter()) {
+        if tree_branch == branch {
+            return tree_value.to_bits() == value.to_bits() || (tree_value.is_nan() && value.is_nan());
+        }
+    }
+    return value.is_nan();
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn evaluators_agree() {
+        let data = run::interpret_text("#frame(-10, -10, 20, 20)\n#resolution(200, 200)\ny = sin(x) + x\ny = 1 / x\ny = tan(x) * 2\ny = pow(x, 0.5)\nx * x + y * y = 16\ny + sin(y) = x\n");
+        for equation in data.equations.iter() {
+            let res = compare(&data, equation);
+            assert!(res.success, "{}", res.exception);
+            assert!(res.samples > 0);
+            assert_eq!(res.mismatches, 0, "Evaluators differ on `{}`.", equation);
+        }
+    }
+}
@@ -0,0 +1,372 @@
+use super::data;
+use super::nodes;
+use super::exceptions;
+
+
+
+// Instructions for the stack based evaluator.
+#[derive(Clone, Copy, Debug)]
+pub enum Instruction {
+    Number {
+        value : f64
+    },
+    VariableX,
+    VariableY,
+
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+
+    Sin,
+    Cos,
+    Tan,
+    Pow,
+    // `slot` is the choice slot of a `±` root, or none for a root typed by the user.
+    Root {
+        slot : Option<usize>
+    }
+}
+
+
+
+// Kinds of discontinuity found in a branch.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Break {
+    None,
+    // The value jumps through infinity at a pole of `tan`.
+    Asymptote,
+    // A divisor, or the base of a negative power, reaches zero.
+    DivisionByZero,
+    // The value is not defined over part of the range.
+    Undefined
+}
+
+
+
+// Expression lowered to a flat list of instructions, evaluated without walking or allocating nodes.
+// Explicit equations are compiled to the side giving `y`, others to `left - right`.
+#[derive(Clone, Debug)]
+pub struct Program {
+    pub instructions : Vec<Instruction>,
+    pub keys         : Vec<u64>,
+    pub explicit     : bool
+}
+impl Program {
+    // Number of branches, one for every combination of `±` root signs. Compiling keeps the number of roots under `COMPILER_MAX_ROOTS`.
+    pub fn branches(&self) -> usize {
+        return 1 << self.keys.len();
+    }
+
+    // Get the choices taken on a branch, matching the branches made by `MultipleValues`.
+    pub fn branch(&self, branch : usize) -> data::Branch {
+        let mut res = data::Branch::new();
+        for (slot, key) in self.keys.iter().enumerate() {
+            res = res.with_choice(*key, (branch >> slot) & 1);
+        }
+        return res;
+    }
+
+    fn choice(branch : usize, slot : Option<usize>) -> bool {
+        return match slot {
+            Some(slot) => (branch >> slot) & 1 == 1,
+            None       => false
+        };
+    }
+
+    // Evaluate a single branch at a point. Undefined values are NaN.
+    pub fn evaluate(&self, x : f64, y : f64, branch : usize, stack : &mut Vec<f64>) -> f64 {
+        stack.clear();
+        for instruction in self.instructions.iter() {
+            let value = match *instruction {
+                Instruction::Number {value} => value,
+                Instruction::VariableX      => x,
+                Instruction::VariableY      => y,
+                Instruction::Sin            => stack.pop().unwrap().sin(),
+                Instruction::Cos            => stack.pop().unwrap().cos(),
+                Instruction::Tan            => stack.pop().unwrap().tan(),
+                Instruction::Root {slot}    => {
+                    let base = stack.pop().unwrap();
+                    let exp  = stack.pop().unwrap();
+                    let v    = if exp % 2.0 != 0.0 || base >= 0.0 {
+                        data::MultipleValues::num_root(exp, base)
+                    } else {
+                        f64::NAN
+                    };
+                    if Program::choice(branch, slot) { -v } else { v }
+                },
+                _                           => {
+                    let right = stack.pop().unwrap();
+                    let left  = stack.pop().unwrap();
+                    match *instruction {
+                        Instruction::Add      => left + right,
+                        Instruction::Subtract => left - right,
+                        Instruction::Multiply => left * right,
+                        Instruction::Divide   => left / right,
+                        _                     => left.powf(right)
+                    }
+                }
+            };
+            stack.push(value);
+        }
+        return stack.pop().unwrap();
+    }
+
+    // Evaluate every branch at a point.
+    pub fn values(&self, x : f64, y : f64, stack : &mut Vec<f64>) -> data::MultipleValues {
+        let mut res = data::MultipleValues {
+            values   : vec![],
+            branches : vec![]
+        };
+        for branch in 0..self.branches() {
+            res.values.push(self.evaluate(x, y, branch, stack));
+            res.branches.push(self.branch(branch));
+        }
+        return res;
+    }
+
+    // Check if the curve might pass through a range of x and y, using the bounds of every branch.
+    pub fn crosses(&self, x : data::Interval, y : data::Interval, stack : &mut Vec<data::Interval>) -> bool {
+        for branch in 0..self.branches() {
+            stack.clear();
+            if self.crosses_from(0, x, y, branch, stack) {
+                return true;
+            }
+        }
+        return false;
+    }
+
+    // Bound the instructions from `start` onwards. When a result is split around an asymptote,
+    // each piece is followed separately, which is the only time the stack is copied.
+    fn crosses_from(&self, start : usize, x : data::Interval, y : data::Interval, branch : usize, stack : &mut Vec<data::Interval>) -> bool {
+        for index in start..self.instructions.len() {
+            let pieces = Program::bound_step(self.instructions[index], x, y, branch, stack);
+            match (pieces.first, pieces.second) {
+                (Some(a), Some(b)) => {
+                    let mut other = stack.clone();
+                    other.push(b);
+                    if self.crosses_from(index + 1, x, y, branch, &mut other) {
+                        return true;
+                    }
+                    stack.push(a);
+                },
+                (Some(a), None)    => stack.push(a),
+                _                  => return false
+            }
+        }
+        // Explicit equations give the values of `y`, others give `left - right` which is 0 on the curve.
+        return match stack.last() {
+            Some(interval) if self.explicit => interval.intersects(&y),
+            Some(interval)                  => interval.contains(0.0),
+            None                            => false
+        };
+    }
+
+    // Find if a branch is discontinuous anywhere in a range of x and y.
+    // The branch breaks where the bounds of a step split around an asymptote, become unbounded or are empty.
+    pub fn find_break(&self, x : data::Interval, y : data::Interval, branch : usize, stack : &mut Vec<data::Interval>) -> Break {
+        stack.clear();
+        for instruction in self.instructions.iter() {
+            let pieces  = Program::bound_step(*instruction, x, y, branch, stack);
+            let bounded = pieces.iter().all(|a| a.lo.is_finite() && a.hi.is_finite());
+            match (pieces.first, pieces.second) {
+                (Some(a), None) if bounded => stack.push(a),
+                (first, _)                 => return match instruction {
+                    Instruction::Tan                    => Break::Asymptote,
+                    Instruction::Divide                 => Break::DivisionByZero,
+                    Instruction::Pow if first.is_some() => Break::DivisionByZero,
+                    _                                   => Break::Undefined
+                }
+            }
+        }
+        return Break::None;
+    }
+
+    // Bound a single instruction, taking its arguments from the stack.
+    fn bound_step(instruction : Instruction, x : data::Interval, y : data::Interval, branch : usize, stack : &mut Vec<data::Interval>) -> data::Pieces {
+        return match instruction {
+            Instruction::Number {value} => data::Pieces::one(data::Interval::new_single(value)),
+            Instruction::VariableX      => data::Pieces::one(x),
+            Instruction::VariableY      => data::Pieces::one(y),
+            Instruction::Sin            => data::Pieces::one(stack.pop().unwrap().sin()),
+            Instruction::Cos            => data::Pieces::one(stack.pop().unwrap().cos()),
+            Instruction::Tan            => stack.pop().unwrap().tan(),
+            Instruction::Root {slot}    => {
+                let base = stack.pop().unwrap();
+                let exp  = stack.pop().unwrap();
+                let mut pieces = base.root(exp);
+                if Program::choice(branch, slot) {
+                    pieces.first  = pieces.first.map(|a| a.neg());
+                    pieces.second = pieces.second.map(|a| a.neg());
+                }
+                pieces
+            },
+            instruction                 => {
+                let right = stack.pop().unwrap();
+                let left  = stack.pop().unwrap();
+                match instruction {
+                    Instruction::Add      => data::Pieces::one(left.add(right)),
+                    Instruction::Subtract => data::Pieces::one(left.sub(right)),
+                    Instruction::Multiply => data::Pieces::one(left.mul(right)),
+                    Instruction::Divide   => left.div(right),
+                    _                     => left.pow(right)
+                }
+            }
+        };
+    }
+}
+
+
+
+// Success/Failure identification class.
+#[derive(Clone, Debug)]
+pub struct CompilerResult {
+    pub success   : bool,
+    pub program   : Program,
+    pub exception : exceptions::RendererException
+}
+
+
+
+// Compiler class
+struct Compiler {
+    program   : Program,
+    exception : exceptions::RendererException
+}
+impl Compiler {
+    // Append the instructions for a node, returning false if it can not be compiled.
+    fn lower(&mut self, node : &nodes::Node) -> bool {
+        let instruction = match &node.base {
+            nodes::NodeBase::Number                  {value}       => Instruction::Number {value : *value},
+            nodes::NodeBase::Variable                {name}        => {
+                if name == "x" {
+                    Instruction::VariableX
+                } else if name == "y" {
+                    Instruction::VariableY
+                } else {
+                    return self.failure(node, exceptions::RendererExceptionBase::InvalidVariableException, format!("Invalid variable `{}` was found.", name));
+                }
+            },
+            nodes::NodeBase::EqualsExpression        {left, right} |
+            nodes::NodeBase::SubtractionOperation    {left, right} => {
+                if ! (self.lower(left) && self.lower(right)) {
+                    return false;
+                }
+                Instruction::Subtract
+            },
+            nodes::NodeBase::AdditionOperation       {left, right} => {
+                if ! (self.lower(left) && self.lower(right)) {
+                    return false;
+                }
+                Instruction::Add
+            },
+            nodes::NodeBase::MultiplicationOperation {left, right} => {
+                if ! (self.lower(left) && self.lower(right)) {
+                    return false;
+                }
+                Instruction::Multiply
+            },
+            nodes::NodeBase::DivisionOperation       {left, right} => {
+                if ! (self.lower(left) && self.lower(right)) {
+                    return false;
+                }
+                Instruction::Divide
+            },
+            nodes::NodeBase::FunctionSin             {a}           => {
+                if ! self.lower(a) {
+                    return false;
+                }
+                Instruction::Sin
+            },
+            nodes::NodeBase::FunctionCos             {a}           => {
+                if ! self.lower(a) {
+                    return false;
+                }
+                Instruction::Cos
+            },
+            nodes::NodeBase::FunctionTan             {a}           => {
+                if ! self.lower(a) {
+                    return false;
+                }
+                Instruction::Tan
+            },
+            nodes::NodeBase::FunctionPow             {base, exp}   => {
+                if ! (self.lower(base) && self.lower(exp)) {
+                    return false;
+                }
+                Instruction::Pow
+            },
+            nodes::NodeBase::FunctionRoot            {exp, base, user_typed} => {
+                if ! (self.lower(exp) && self.lower(base)) {
+                    return false;
+                }
+                // Repeated `±` roots share a slot, so they take the same sign.
+                let mut slot = None;
+                if ! *user_typed {
+                    let key = node.key();
+                    slot = match self.program.keys.iter().position(|k| *k == key) {
+                        Some(index) => Some(index),
+                        None        => {
+                            if self.program.keys.len() >= data::COMPILER_MAX_ROOTS {
+                                return self.failure(node, exceptions::RendererExceptionBase::TooManyBranchesException, format!("Equations can not hold more than {} `±` roots.", data::COMPILER_MAX_ROOTS));
+                            }
+                            self.program.keys.push(key);
+                            Some(self.program.keys.len() - 1)
+                        }
+                    };
+                }
+                Instruction::Root {slot : slot}
+            },
+            _                                                      => {
+                return self.failure(node, exceptions::RendererExceptionBase::InternalException, format!("Unknown node `{}` found.", node));
+            }
+        };
+        self.program.instructions.push(instruction);
+        return true;
+    }
+
+    fn failure(&mut self, node : &nodes::Node, base : exceptions::RendererExceptionBase, message : String) -> bool {
+        self.exception = exceptions::RendererException {
+            base    : base,
+            message : message,
+            range   : node.range.clone()
+        };
+        return false;
+    }
+}
+
+
+
+// Function for compiling an equation.
+pub fn compile(equation : &nodes::Node) -> CompilerResult {
+    let mut compiler = Compiler {
+        program   : Program {
+            instructions : vec![],
+            keys         : vec![],
+            explicit     : equation.is_explicit()
+        },
+        exception : exceptions::RendererException {
+            base    : exceptions::RendererExceptionBase::NoException,
+            message : "".to_string(),
+            range   : equation.range.clone()
+        }
+    };
+
+    // Explicit equations only need the side which gives `y`.
+    let mut node = equation;
+    if compiler.program.explicit {
+        match &equation.base {
+            nodes::NodeBase::EqualsExpression {left, right} => {
+                node = if right.contains_variable("y") { left } else { right };
+            },
+            _ => ()
+        }
+    }
+
+    let success = compiler.lower(node);
+    return CompilerResult {
+        success   : success,
+        program   : compiler.program,
+        exception : compiler.exception
+    };
+}
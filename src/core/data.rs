@@ -1,4 +1,7 @@
 use std::ops;
+use std::f64::consts::PI;
+
+use serde::{Serialize, Deserialize};
 
 
 
@@ -7,11 +10,31 @@ pub const ALPHABETIC : &'static str      = "abcdefghijklmnopqrstuvwxyz";
 pub const NUMERIC    : &'static str      = "0123456789";
 
 // Header functions for defining export settings.
-pub const HEADFUNCS  : [&'static str; 4] = [
+pub const HEADFUNCS  : [&'static str; 24] = [
     "frame",
     "resolution",
     "export",
-    "print_now"
+    "print_now",
+    "division_by_zero",
+    "thickness",
+    "antialias",
+    "units",
+    "power",
+    "speed",
+    "passes",
+    "origin",
+    "raster",
+    "overscan",
+    "skip_blank",
+    "axes",
+    "tolerance",
+    "optimise_travel",
+    "kerf",
+    "hatch",
+    "layer",
+    "machine",
+    "size",
+    "dpi"
 ];
 // Functions that can be used in expressions.
 pub const FUNCTIONS  : [&'static str; 5] = [
@@ -21,9 +44,255 @@ pub const FUNCTIONS  : [&'static str; 5] = [
     "root",
     "pow"
 ];
+// Attributes that can be given in brackets after an equation to style it.
+pub const ATTRIBUTES : [&'static str; 3] = [
+    "colour",
+    "width",
+    "dash"
+];
 
 pub const ROOT_MAX_RECURSION : i32 = 25;
 
+// Numeric solver settings for equations where `y` can not be isolated.
+pub const SOLVER_MAX_SAMPLES    : i32 = 2048;
+pub const SOLVER_MAX_ITERATIONS : i32 = 50;
+pub const SOLVER_TOLERANCE      : f64 = 1e-9;
+
+// Most `±` roots an equation can hold. Every combination of their signs is a branch, evaluated at every sample.
+pub const COMPILER_MAX_ROOTS    : usize = 16;
+
+// Number of times a segment between two samples can be halved to get it shorter than a pixel.
+pub const TRACER_MAX_DEPTH      : i32 = 10;
+// Number of steps on each side of a segment when searching for the curve at right angles to it.
+pub const TRACER_SEARCH_STEPS   : i32 = 8;
+
+// Number of image rows in each band the renderer hands out to a thread.
+pub const RENDERER_BAND_HEIGHT  : i32 = 64;
+
+// Largest arc radius fitted, as a multiple of the distance between its ends, number of attempts to improve a fitted curve,
+// and number of places a fitted curve is checked against each segment of the polyline.
+pub const FITTING_MAX_ARC_RATIO : f64 = 100.0;
+pub const FITTING_ITERATIONS    : i32 = 4;
+pub const FITTING_CHECKS        : i32 = 8;
+
+// Number of times every pair of paths is tried for a shorter order, and most paths the pairs are tried for.
+pub const ORDERING_PASSES       : i32   = 8;
+pub const ORDERING_MAX_PATHS    : usize = 2000;
+
+// Longest mitred corner on an offset path, as a multiple of the offset, and angle between points on a rounded corner.
+pub const OFFSET_MITER_LIMIT    : f64 = 2.0;
+pub const OFFSET_ROUND_STEP     : f64 = PI / 18.0;
+
+// Spindle value giving full laser power, matching GRBL's default `$30`. Used unless a machine profile gives its own.
+pub const GCODE_MAX_SPINDLE     : f64 = 1000.0;
+
+// Directory holding machine profiles, looked for beside the script and then in the working directory.
+pub const MACHINE_DIRECTORY     : &'static str = "machines";
+
+// HPGL plotter units in a millimetre, and number of pens a plotter holds.
+pub const HPGL_UNITS_PER_MILLIMETRE : f64   = 40.0;
+pub const HPGL_PENS                 : usize = 8;
+
+// Colours given to layers in turn, with the nearest AutoCAD colour index for DXF.
+pub const LAYER_COLOURS             : [(Colour, i32); 7] = [
+    (Colour {r : 0.0, g : 0.0, b : 1.0, a : 1.0}, 5),
+    (Colour {r : 1.0, g : 0.0, b : 0.0, a : 1.0}, 1),
+    (Colour {r : 0.0, g : 0.6, b : 0.0, a : 1.0}, 3),
+    (Colour {r : 1.0, g : 0.5, b : 0.0, a : 1.0}, 30),
+    (Colour {r : 0.0, g : 0.6, b : 0.6, a : 1.0}, 4),
+    (Colour {r : 0.8, g : 0.0, b : 0.8, a : 1.0}, 6),
+    (Colour {r : 0.6, g : 0.6, b : 0.0, a : 1.0}, 2)
+];
+
+// Colours that can be given to the `colour` attribute by name.
+pub const COLOUR_NAMES              : [(&'static str, Colour); 10] = [
+    ("black",   Colour {r : 0.0, g : 0.0, b : 0.0, a : 1.0}),
+    ("white",   Colour {r : 1.0, g : 1.0, b : 1.0, a : 1.0}),
+    ("grey",    Colour {r : 0.5, g : 0.5, b : 0.5, a : 1.0}),
+    ("red",     Colour {r : 1.0, g : 0.0, b : 0.0, a : 1.0}),
+    ("orange",  Colour {r : 1.0, g : 0.5, b : 0.0, a : 1.0}),
+    ("yellow",  Colour {r : 1.0, g : 1.0, b : 0.0, a : 1.0}),
+    ("green",   Colour {r : 0.0, g : 0.6, b : 0.0, a : 1.0}),
+    ("cyan",    Colour {r : 0.0, g : 1.0, b : 1.0, a : 1.0}),
+    ("blue",    Colour {r : 0.0, g : 0.0, b : 1.0, a : 1.0}),
+    ("magenta", Colour {r : 1.0, g : 0.0, b : 1.0, a : 1.0})
+];
+
+// AutoCAD colour indices a colour is matched to for DXF, with the colours they stand for.
+pub const ACI_COLOURS               : [(Colour, i32); 10] = [
+    (Colour {r : 1.0,  g : 0.0,  b : 0.0,  a : 1.0}, 1),
+    (Colour {r : 1.0,  g : 1.0,  b : 0.0,  a : 1.0}, 2),
+    (Colour {r : 0.0,  g : 1.0,  b : 0.0,  a : 1.0}, 3),
+    (Colour {r : 0.0,  g : 1.0,  b : 1.0,  a : 1.0}, 4),
+    (Colour {r : 0.0,  g : 0.0,  b : 1.0,  a : 1.0}, 5),
+    (Colour {r : 1.0,  g : 0.0,  b : 1.0,  a : 1.0}, 6),
+    (Colour {r : 0.0,  g : 0.0,  b : 0.0,  a : 1.0}, 7),
+    (Colour {r : 0.5,  g : 0.5,  b : 0.5,  a : 1.0}, 8),
+    (Colour {r : 0.75, g : 0.75, b : 0.75, a : 1.0}, 9),
+    (Colour {r : 1.0,  g : 0.5,  b : 0.0,  a : 1.0}, 30)
+];
+
+// Number of layers in a LightBurn project.
+pub const LIGHTBURN_LAYERS          : usize = 30;
+
+
+
+// How a problem found while rendering is reported.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ExceptionLevel {
+    // Log a warning and carry on.
+    Warning,
+    // Stop with an exception.
+    Error
+}
+
+
+
+// Order that paths are cut in.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Ordering {
+    // The order they are traced in.
+    Script,
+    // The order giving the least travel.
+    Order,
+    // The order giving the least travel when open paths may be cut backwards and loops started anywhere.
+    Reverse
+}
+
+
+
+// Way of joining the edges of an offset path around a corner.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Join {
+    // Extend the edges until they meet, cutting the corner off where they would meet too far away.
+    Miter,
+    // Go around the corner on a circle.
+    Round
+}
+
+
+
+// Width of the cut made by the beam, in units, and how corners are joined when paths are offset by half of it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Kerf {
+    pub width : f64,
+    pub join  : Join
+}
+
+
+
+// Lines filling the closed paths of an equation, `spacing` units apart at `angle` degrees anticlockwise from the x axis.
+// `cross` adds lines at right angles, `alternate` runs every other line backwards, and `outline` keeps the paths filled.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Hatch {
+    pub spacing   : f64,
+    pub angle     : f64,
+    pub cross     : bool,
+    pub alternate : bool,
+    pub outline   : bool
+}
+
+
+
+// Way the equations on a layer are burnt.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LayerMode {
+    // Cut through along the paths.
+    Cut,
+    // Mark along the paths without cutting through.
+    Score,
+    // Fill in the rendered image row by row.
+    Engrave
+}
+impl LayerMode {
+    // Get the name used in scripts.
+    pub fn name(&self) -> &'static str {
+        return match self {
+            LayerMode::Cut     => "cut",
+            LayerMode::Score   => "score",
+            LayerMode::Engrave => "engrave"
+        };
+    }
+
+    // Get when layers are burnt, lowest first. Cutting comes last, so parts are not moved by cutting them free.
+    pub fn priority(&self) -> usize {
+        return match self {
+            LayerMode::Engrave => 0,
+            LayerMode::Score   => 1,
+            LayerMode::Cut     => 2
+        };
+    }
+}
+
+
+
+// Laser settings for the equations after a `#layer`, with power as a percentage and speed in units per minute.
+// The kerf is set by a `#kerf` after the `#layer`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Layer {
+    pub name   : String,
+    pub mode   : LayerMode,
+    pub power  : f64,
+    pub speed  : f64,
+    pub passes : i32,
+    pub kerf   : Kerf
+}
+
+
+
+// Laser a job is run on, read from a TOML profile. Sizes are in millimetres and feeds in millimetres per minute.
+// The bed runs from the machine's zero to its width and height.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Machine {
+    #[serde(skip)]
+    pub name        : String,
+    pub bed_width   : f64,
+    pub bed_height  : f64,
+    // Spindle value giving full power, like GRBL's `$30`.
+    pub max_spindle : f64,
+    pub max_feed    : f64,
+    // Highest power allowed as a percentage, if the laser should not be run at full power.
+    pub max_power   : Option<f64>
+}
+
+
+
+// Drawing style of an equation, from the attributes after it. Attributes not given fall back to the layer colour and `#thickness`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Style {
+    pub colour : Option<Colour>,
+    pub width  : Option<f64>,
+    // Lengths of dashes and gaps in turn, in units. Empty for a solid line.
+    pub dash   : Vec<f64>
+}
+
+
+
+// Physical unit that positions on the bed are given in. One frame unit is one unit unless `#size` scales the frame.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Unit {
+    Millimetre,
+    Inch
+}
+impl Unit {
+    // Get the unit's name as used in scripts and SVG lengths.
+    pub fn name(&self) -> &'static str {
+        return match self {
+            Unit::Millimetre => "mm",
+            Unit::Inch       => "in"
+        };
+    }
+
+    // Get the length of the unit in millimetres.
+    pub fn millimetres(&self) -> f64 {
+        return match self {
+            Unit::Millimetre => 1.0,
+            Unit::Inch       => 25.4
+        };
+    }
+}
+
 
 
 // Range struct used for identifying where characters came from.
@@ -45,76 +314,162 @@ pub struct Vector2 {
 
 
 
+// Point in frame coordinates, used for traced curves.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Point {
+    pub x : f64,
+    pub y : f64
+}
+
+
+
 // Better colour class to make rendering code prettier.
-#[derive(Clone, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Colour {
     pub r : f32,
     pub g : f32,
     pub b : f32,
     pub a : f32
 }
+impl Colour {
+    // Read a colour given as `#rgb`, `#rrggbb` or a name in `COLOUR_NAMES`.
+    pub fn parse(text : &str) -> Option<Colour> {
+        if let Some((_, colour)) = COLOUR_NAMES.iter().find(|(name, _)| *name == text) {
+            return Some(*colour);
+        }
+        let digits = text.strip_prefix('#')?;
+        if ! digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        // Each digit of the short form is doubled, so `#f80` is `#ff8800`.
+        let channels = match digits.len() {
+            3 => digits.chars().map(|c| u8::from_str_radix(&c.to_string().repeat(2), 16).unwrap()).collect::<Vec<u8>>(),
+            6 => (0..3).map(|i| u8::from_str_radix(&digits[(i * 2)..(i * 2 + 2)], 16).unwrap()).collect::<Vec<u8>>(),
+            _ => return None
+        };
+        return Some(Colour {
+            r : channels[0] as f32 / 255.0,
+            g : channels[1] as f32 / 255.0,
+            b : channels[2] as f32 / 255.0,
+            a : 1.0
+        });
+    }
+
+    // Get the nearest AutoCAD colour index in `ACI_COLOURS`.
+    pub fn aci(&self) -> i32 {
+        let distance = |other : &Colour| (self.r - other.r).powi(2) + (self.g - other.g).powi(2) + (self.b - other.b).powi(2);
+        return ACI_COLOURS.iter().min_by(|a, b| distance(&a.0).total_cmp(&distance(&b.0))).unwrap().1;
+    }
+}
+
+
+
+// Choices made at each multi-valued function, so repeated uses of the same expression take the same value.
+// Each choice is the key of the function expression and the index of the value that was taken.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Branch {
+    pub choices : Vec<(u64, usize)>
+}
+impl Branch {
+    pub fn new() -> Branch {
+        return Branch {
+            choices : vec![]
+        };
+    }
+
+    pub fn with_choice(&self, key : u64, choice : usize) -> Branch {
+        let mut res = self.clone();
+        match res.choices.binary_search_by_key(&key, |c| c.0) {
+            Ok(index)  => res.choices[index] = (key, choice),
+            Err(index) => res.choices.insert(index, (key, choice))
+        }
+        return res;
+    }
+
+    // Combine the choices of two branches, if they agree on every shared choice.
+    pub fn merge(&self, other : &Branch) -> Option<Branch> {
+        let mut res = self.clone();
+        for (key, choice) in other.choices.iter().copied() {
+            match res.choices.binary_search_by_key(&key, |c| c.0) {
+                Ok(index)  => if res.choices[index].1 != choice {
+                    return None;
+                },
+                Err(index) => res.choices.insert(index, (key, choice))
+            }
+        }
+        return Some(res);
+    }
+}
 
 
 
-// Renderer multiple values.
+// Renderer multiple values, each belonging to a branch.
 #[derive(Clone, Debug)]
 pub struct MultipleValues {
-    pub values : Vec<f32>
+    pub values   : Vec<f64>,
+    pub branches : Vec<Branch>
 }
 impl MultipleValues {
-    pub fn new_single(value : f32) -> MultipleValues {
+    pub fn new_single(value : f64) -> MultipleValues {
         return MultipleValues {
-            values : vec![value]
+            values   : vec![value],
+            branches : vec![Branch::new()]
         };
     }
 
-    pub fn sin(self) -> MultipleValues {
-        let mut res = vec![];
-        for x in self.values {
-            res.push(x.sin());
-        }
+    // Apply a function to every value, keeping its branch.
+    fn map<F : Fn(f64) -> f64>(self, func : F) -> MultipleValues {
         return MultipleValues {
-            values : res
+            values   : self.values.into_iter().map(func).collect(),
+            branches : self.branches
+        };
+    }
+
+    // Apply a function to every pair of values from compatible branches.
+    fn combine<F : Fn(f64, f64) -> f64>(self, other : MultipleValues, func : F) -> MultipleValues {
+        let mut res = MultipleValues {
+            values   : vec![],
+            branches : vec![]
+        };
+        for (x, x_branch) in self.values.iter().zip(self.branches.iter()) {
+            for (y, y_branch) in other.values.iter().zip(other.branches.iter()) {
+                match x_branch.merge(y_branch) {
+                    Some(branch) => {
+                        res.values.push(func(*x, *y));
+                        res.branches.push(branch);
+                    },
+                    None         => ()
+                }
+            }
         }
+        return res;
+    }
+
+    pub fn sin(self) -> MultipleValues {
+        return self.map(|x| x.sin());
     }
 
     pub fn cos(self) -> MultipleValues {
-        let mut res = vec![];
-        for x in self.values {
-            res.push(x.cos());
-        }
-        return MultipleValues {
-            values : res
-        }
+        return self.map(|x| x.cos());
     }
 
     pub fn tan(self) -> MultipleValues {
-        let mut res = vec![];
-        for x in self.values {
-            res.push(x.tan());
-        }
-        return MultipleValues {
-            values : res
-        }
+        return self.map(|x| x.tan());
     }
 
     pub fn pow(self, exp : MultipleValues) -> MultipleValues {
-        let mut res = vec![];
-        for x in self.values {
-            for y in exp.values.clone() {
-                res.push(x.powf(y));
-            }
-        }
-        return MultipleValues {
-            values : res
-        }
+        return self.combine(exp, |x, y| x.powf(y));
     }
 
-    fn num_root(self, exp : f32, main : f32) -> f32 {
-        let p      = 1e-9_f32;
-        let mut x0 = main / exp;
+    // Newton's method, started from the power so it converges in a few steps for very large and very small values.
+    pub fn num_root(exp : f64, main : f64) -> f64 {
+        if main == 0.0 {
+            return 0.0;
+        }
+        let p      = 1e-9_f64;
+        let mut x0 = main.abs().powf(1.0 / exp).copysign(main);
         for _i in 0..ROOT_MAX_RECURSION {
-            let x1 = ((exp - 1.0) * x0 + main / f32::powf(x0, exp - 1.0)) / exp;
+            let x1 = ((exp - 1.0) * x0 + main / f64::powf(x0, exp - 1.0)) / exp;
             if (x1 - x0).abs() < (x0 * p).abs() {
                 x0 = x1;
                 break;
@@ -124,110 +479,348 @@ impl MultipleValues {
         return x0;
     }
 
-    pub fn root(self, exp : MultipleValues, user_typed : bool) -> MultipleValues {
-        let mut res = vec![];
-        for x in self.values.clone() {
-            for y in exp.values.clone() {
-                if y % 2.0 != 0.0 || x >= 0.0 {
-                    let v = self.clone().num_root(y, x);
-                    res.push(v);
-                    if ! user_typed {
-                        res.push(-v);
+    // `key` identifies the root expression, so the sign chosen for it is shared by every use.
+    pub fn root(self, exp : MultipleValues, user_typed : bool, key : u64) -> MultipleValues {
+        let mut res = MultipleValues {
+            values   : vec![],
+            branches : vec![]
+        };
+        for (x, x_branch) in self.values.iter().zip(self.branches.iter()) {
+            for (y, y_branch) in exp.values.iter().zip(exp.branches.iter()) {
+                let branch = match x_branch.merge(y_branch) {
+                    Some(branch) => branch,
+                    None         => continue
+                };
+                if y % 2.0 != 0.0 || *x >= 0.0 {
+                    let v = MultipleValues::num_root(*y, *x);
+                    if user_typed {
+                        res.values.push(v);
+                        res.branches.push(branch);
+                    } else {
+                        res.values.push(v);
+                        res.branches.push(branch.with_choice(key, 0));
+                        res.values.push(-v);
+                        res.branches.push(branch.with_choice(key, 1));
                     }
                 }
             }
         }
-        return MultipleValues {
-            values : res
-        }
-    }
-
-    pub fn abs(self) -> MultipleValues {
-        let mut res = vec![];
-        for x in self.values {
-            res.push(x.abs());
-        }
-        return MultipleValues {
-            values : res
-        }
+        return res;
     }
 }
 impl ops::Add for MultipleValues {
     type Output = Self;
     fn add(self, other: MultipleValues) -> Self {
-        let mut res = vec![];
-        for x in self.values {
-            for y in other.values.clone() {
-                res.push(x + y);
-            }
-        }
-        return MultipleValues {
-            values : res
-        };
+        return self.combine(other, |x, y| x + y);
     }
 }
 impl ops::Sub for MultipleValues {
     type Output = Self;
     fn sub(self, other: MultipleValues) -> Self {
-        let mut res = vec![];
-        for x in self.values {
-            for y in other.values.clone() {
-                res.push(x - y);
-            }
-        }
-        return MultipleValues {
-            values : res
-        };
+        return self.combine(other, |x, y| x - y);
     }
 }
 impl ops::Mul for MultipleValues {
     type Output = Self;
     fn mul(self, other: MultipleValues) -> Self {
-        let mut res = vec![];
-        for x in self.values {
-            for y in other.values.clone() {
-                res.push(x * y);
-            }
-        }
-        return MultipleValues {
-            values : res
-        };
+        return self.combine(other, |x, y| x * y);
     }
 }
 impl ops::Div for MultipleValues {
     type Output = Self;
     fn div(self, other: MultipleValues) -> Self {
-        let mut res = vec![];
-        for x in self.values {
-            for y in other.values.clone() {
-                res.push(x / y);
-            }
-        }
-        return MultipleValues {
-            values : res
-        };
+        return self.combine(other, |x, y| x / y);
     }
 }
 impl ops::Neg for MultipleValues {
     type Output = Self;
     fn neg(self) -> Self {
-        let mut res = vec![];
-        for x in self.values {
-            res.push(-x);
+        return self.map(|x| -x);
+    }
+}
+
+
+
+// Closed range of values, used for guaranteed bounds over a pixel column or cell.
+// Bounds worked out from other values are moved out by a step of the last bit, so rounding can not leave a value outside.
+#[derive(Clone, Copy, Debug)]
+pub struct Interval {
+    pub lo : f64,
+    pub hi : f64
+}
+impl Interval {
+    pub fn new(a : f64, b : f64) -> Interval {
+        return Interval {
+            lo : a.min(b).next_down(),
+            hi : a.max(b).next_up()
+        };
+    }
+
+    pub fn new_single(value : f64) -> Interval {
+        return Interval {
+            lo : value,
+            hi : value
+        };
+    }
+
+    pub fn entire() -> Interval {
+        return Interval {
+            lo : f64::NEG_INFINITY,
+            hi : f64::INFINITY
+        };
+    }
+
+    pub fn width(&self) -> f64 {
+        return self.hi - self.lo;
+    }
+
+    pub fn contains(&self, value : f64) -> bool {
+        return self.lo <= value && value <= self.hi;
+    }
+
+    pub fn intersects(&self, other : &Interval) -> bool {
+        return self.lo <= other.hi && other.lo <= self.hi;
+    }
+
+    fn is_valid(&self) -> bool {
+        return ! (self.lo.is_nan() || self.hi.is_nan());
+    }
+
+    // Check if the interval is a single integer, for exact power and root handling.
+    fn as_integer(&self) -> Option<f64> {
+        if self.lo == self.hi && self.lo.fract() == 0.0 {
+            return Some(self.lo);
         }
-        return MultipleValues {
-            values : res
+        return None;
+    }
+
+    // Smallest interval containing all of the given values, ignoring undefined ones.
+    fn hull(values : &[f64]) -> Interval {
+        let mut res = Interval {
+            lo : f64::INFINITY,
+            hi : f64::NEG_INFINITY
+        };
+        for value in values.iter().copied() {
+            if ! value.is_nan() {
+                res.lo = res.lo.min(value);
+                res.hi = res.hi.max(value);
+            }
+        }
+        if res.lo > res.hi {
+            res.lo = f64::NAN;
+            res.hi = f64::NAN;
+        }
+        res.lo = res.lo.next_down();
+        res.hi = res.hi.next_up();
+        return res;
+    }
+
+    // Check if `start + period * k` lies in the interval for some integer k.
+    fn contains_periodic(&self, start : f64, period : f64) -> bool {
+        let k = ((self.lo - start) / period).ceil();
+        return start + period * k <= self.hi;
+    }
+
+    pub fn add(self, other : Interval) -> Interval {
+        return Interval::new(self.lo + other.lo, self.hi + other.hi);
+    }
+
+    pub fn sub(self, other : Interval) -> Interval {
+        return Interval::new(self.lo - other.hi, self.hi - other.lo);
+    }
+
+    pub fn mul(self, other : Interval) -> Interval {
+        // Treat `0 * inf` as 0, since the zero is exact.
+        let product = |a : f64, b : f64| if a == 0.0 || b == 0.0 { 0.0 } else { a * b };
+        return Interval::hull(&[
+            product(self.lo, other.lo),
+            product(self.lo, other.hi),
+            product(self.hi, other.lo),
+            product(self.hi, other.hi)
+        ]);
+    }
+
+    pub fn neg(self) -> Interval {
+        return Interval::new(-self.hi, -self.lo);
+    }
+
+    pub fn sin(self) -> Interval {
+        return self.wave(f64::sin, PI / 2.0);
+    }
+
+    pub fn cos(self) -> Interval {
+        return self.wave(f64::cos, 0.0);
+    }
+
+    // Bounds of `sin` or `cos`, which peak at `peak` and dip half a turn later.
+    fn wave(self, f : fn(f64) -> f64, peak : f64) -> Interval {
+        if self.width() >= 2.0 * PI {
+            return Interval::new_single(-1.0).join(Interval::new_single(1.0));
+        }
+        let mut res = Interval::new(f(self.lo), f(self.hi));
+        if self.contains_periodic(peak, 2.0 * PI) {
+            res.hi = 1.0;
+        }
+        if self.contains_periodic(peak + PI, 2.0 * PI) {
+            res.lo = -1.0;
+        }
+        return Interval::new_single(res.lo.max(-1.0)).join(Interval::new_single(res.hi.min(1.0)));
+    }
+
+    // Smallest interval holding both, without widening it.
+    fn join(self, other : Interval) -> Interval {
+        return Interval {
+            lo : self.lo.min(other.lo),
+            hi : self.hi.max(other.hi)
         };
     }
+
+    // Tan splits into two pieces when the interval crosses an asymptote.
+    pub fn tan(self) -> Pieces {
+        if self.width() >= PI {
+            return Pieces::one(Interval::entire());
+        }
+        if self.contains_periodic(PI / 2.0, PI) {
+            return Pieces::two(
+                Interval::new(self.lo.tan(), f64::INFINITY),
+                Interval::new(f64::NEG_INFINITY, self.hi.tan())
+            );
+        }
+        return Pieces::one(Interval::new(self.lo.tan(), self.hi.tan()));
+    }
+
+    pub fn pow(self, exp : Interval) -> Pieces {
+        match exp.as_integer() {
+            Some(n) if n >= 0.0 => {
+                let lo = self.lo.powf(n);
+                let hi = self.hi.powf(n);
+                if n % 2.0 == 0.0 && self.contains(0.0) {
+                    return Pieces::one(Interval::new(0.0, lo.max(hi)));
+                }
+                return Pieces::one(Interval::new(lo, hi));
+            },
+            Some(n) => {
+                let mut res = Pieces::none();
+                for power in self.pow(Interval::new_single(-n)).iter() {
+                    res = res.join(Interval::new_single(1.0).div(power));
+                }
+                return res;
+            },
+            None    => ()
+        }
+        // Non-integer powers are only defined for positive bases, and `exp * ln(base)` is extreme at the corners.
+        if self.hi < 0.0 {
+            return Pieces::none();
+        }
+        // The base is only cut down to the defined part, so it is not widened, which would take it below zero again.
+        let base    = Interval {
+            lo : self.lo.max(0.0),
+            hi : self.hi
+        };
+        let mut res = Interval::hull(&[
+            base.lo.powf(exp.lo),
+            base.lo.powf(exp.hi),
+            base.hi.powf(exp.lo),
+            base.hi.powf(exp.hi)
+        ]);
+        if exp.contains(0.0) {
+            res.lo = res.lo.min(1.0);
+            res.hi = res.hi.max(1.0);
+        }
+        return Pieces::one(res);
+    }
+
+    // Real nth root, keeping the sign for odd roots.
+    pub fn root(self, exp : Interval) -> Pieces {
+        match exp.as_integer() {
+            Some(n) if n % 2.0 != 0.0 => {
+                let root = |v : f64| v.signum() * v.abs().powf(1.0 / n);
+                return Pieces::one(Interval::new(root(self.lo), root(self.hi)));
+            },
+            _ => {
+                let mut res = Pieces::none();
+                for inverse in Interval::new_single(1.0).div(exp).iter() {
+                    res = res.join(self.pow(inverse));
+                }
+                return res;
+            }
+        }
+    }
+
+    // Division splits into two pieces when the divisor crosses zero.
+    pub fn div(self, other : Interval) -> Pieces {
+        if ! other.contains(0.0) {
+            return Pieces::one(Interval::hull(&[
+                self.lo / other.lo,
+                self.lo / other.hi,
+                self.hi / other.lo,
+                self.hi / other.hi
+            ]));
+        }
+        if self.contains(0.0) || (other.lo == 0.0 && other.hi == 0.0) {
+            return Pieces::one(Interval::entire());
+        }
+        let a = if self.lo > 0.0 { self.lo } else { self.hi };
+        if other.lo == 0.0 {
+            return Pieces::one(Interval::new(a / other.hi, f64::INFINITY.copysign(a)));
+        }
+        if other.hi == 0.0 {
+            return Pieces::one(Interval::new(a / other.lo, f64::INFINITY.copysign(-a)));
+        }
+        return Pieces::two(
+            Interval::new(a / other.lo, f64::INFINITY.copysign(-a)),
+            Interval::new(a / other.hi, f64::INFINITY.copysign(a))
+        );
+    }
 }
 
 
 
-// Renderer pixel left-right values.
-#[derive(Clone, Debug)]
-pub struct LeftRight {
-    pub left  : MultipleValues,
-    pub right : MultipleValues
+// Result of an interval function, which is empty where undefined or split in two around an asymptote.
+#[derive(Clone, Copy, Debug)]
+pub struct Pieces {
+    pub first  : Option<Interval>,
+    pub second : Option<Interval>
+}
+impl Pieces {
+    pub fn none() -> Pieces {
+        return Pieces {
+            first  : None,
+            second : None
+        };
+    }
+
+    pub fn one(a : Interval) -> Pieces {
+        return Pieces::none().push(a);
+    }
+
+    pub fn two(a : Interval, b : Interval) -> Pieces {
+        return Pieces::none().push(a).push(b);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Interval> {
+        return self.first.into_iter().chain(self.second);
+    }
+
+    // Add a piece, dropping undefined ones. A third piece is merged into the second to keep the bounds safe.
+    fn push(mut self, a : Interval) -> Pieces {
+        if ! a.is_valid() {
+            return self;
+        }
+        match (self.first, self.second) {
+            (None, _)          => self.first  = Some(a),
+            (Some(_), None)    => self.second = Some(a),
+            (Some(_), Some(b)) => self.second = Some(Interval::new(a.lo.min(b.lo), a.hi.max(b.hi)))
+        }
+        return self;
+    }
+
+    fn join(mut self, other : Pieces) -> Pieces {
+        for a in other.iter() {
+            self = self.push(a);
+        }
+        return self;
+    }
 }
 
 
@@ -254,3 +847,91 @@ pub fn escapify(string: String) -> String {
     }
     return ret;
 }
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Check every defined value of `f` across the interval, including both ends, lies within one of the pieces.
+    fn encloses(pieces : Pieces, interval : Interval, f : impl Fn(f64) -> f64) {
+        for step in 0..=1000 {
+            let x = (interval.lo + interval.width() * step as f64 / 1000.0).min(interval.hi);
+            let y = f(x);
+            if y.is_nan() {
+                continue;
+            }
+            assert!(pieces.iter().any(|piece| piece.contains(y)), "f({}) = {} is outside {:?}.", x, y, pieces);
+        }
+    }
+
+    fn bounds(pieces : Pieces) -> Vec<(f64, f64)> {
+        return pieces.iter().map(|piece| (piece.lo, piece.hi)).collect();
+    }
+
+    fn close(pieces : Pieces, expected : &[(f64, f64)]) -> bool {
+        let found = bounds(pieces);
+        let near  = |a : f64, b : f64| a == b || (a - b).abs() < 1e-12;
+        return found.len() == expected.len() && found.iter().zip(expected).all(|(a, b)| near(a.0, b.0) && near(a.1, b.1));
+    }
+
+    #[test]
+    fn sin_reaches_its_peaks() {
+        for (lo, hi) in [(0.0, PI), (-0.1, 7.0), (1.0, 1.5), (-2.0 * PI - 1.0, -2.0 * PI + 1.0), (3.0, 5.0)] {
+            let interval = Interval::new(lo, hi);
+            encloses(Pieces::one(interval.sin()), interval, f64::sin);
+            encloses(Pieces::one(interval.cos()), interval, f64::cos);
+        }
+        assert_eq!(Interval::new(0.0, PI).sin().hi, 1.0);
+        assert_eq!(Interval::new(3.0, 5.0).sin().lo, -1.0);
+        assert!(close(Pieces::one(Interval::new(-0.1, 7.0).sin()), &[(-1.0, 1.0)]));
+    }
+
+    #[test]
+    fn tan_splits_at_asymptotes() {
+        let across = Interval::new(1.0, 2.0);
+        assert!(close(across.tan(), &[(1.0f64.tan(), f64::INFINITY), (f64::NEG_INFINITY, 2.0f64.tan())]));
+        encloses(across.tan(), across, f64::tan);
+        let within = Interval::new(-1.0, 1.0);
+        assert!(close(within.tan(), &[(-(1.0f64.tan()), 1.0f64.tan())]));
+        assert!(close(Interval::new(0.0, 4.0).tan(), &[(f64::NEG_INFINITY, f64::INFINITY)]));
+    }
+
+    #[test]
+    fn div_splits_at_zero() {
+        let one = Interval::new_single(1.0);
+        assert!(close(one.div(Interval::new(-1.0, 2.0)), &[(f64::NEG_INFINITY, -1.0), (0.5, f64::INFINITY)]));
+        encloses(one.div(Interval::new(-1.0, 2.0)), Interval::new(-1.0, 2.0), |x| 1.0 / x);
+        // Divisors ending exactly at zero are only infinite on one side.
+        assert!(close(one.div(Interval {lo : 0.0, hi : 2.0}), &[(0.5, f64::INFINITY)]));
+        assert!(close(one.div(Interval {lo : -2.0, hi : 0.0}), &[(f64::NEG_INFINITY, -0.5)]));
+        assert!(close(Interval::new(-1.0, 1.0).div(Interval::new(-1.0, 1.0)), &[(f64::NEG_INFINITY, f64::INFINITY)]));
+        assert!(close(Interval::new(2.0, 4.0).div(Interval::new(1.0, 2.0)), &[(1.0, 4.0)]));
+    }
+
+    #[test]
+    fn pow_and_root_bounds() {
+        let across = Interval::new(-2.0, 3.0);
+        assert!(close(across.pow(Interval::new_single(2.0)), &[(0.0, 9.0)]));
+        assert!(close(across.pow(Interval::new_single(3.0)), &[(-8.0, 27.0)]));
+        encloses(across.pow(Interval::new_single(-1.0)), across, |x| 1.0 / x);
+        assert_eq!(bounds(across.pow(Interval::new_single(-1.0))).len(), 2);
+        assert!(close(Interval::new(0.0, 4.0).pow(Interval::new_single(0.5)), &[(0.0, 2.0)]));
+        assert!(close(Interval::new(-2.0, -1.0).pow(Interval::new_single(0.5)), &[]));
+        encloses(Interval::new(0.5, 2.0).pow(Interval::new(1.0, 3.0)), Interval::new(0.5, 2.0), |x| x.powf(2.0));
+
+        assert!(close(Interval::new(-8.0, 27.0).root(Interval::new_single(3.0)), &[(-2.0, 3.0)]));
+        encloses(Interval::new(-8.0, 27.0).root(Interval::new_single(3.0)), Interval::new(-8.0, 27.0), f64::cbrt);
+        // Even roots are only defined from zero up.
+        assert!(close(Interval::new(-4.0, 9.0).root(Interval::new_single(2.0)), &[(0.0, 3.0)]));
+        assert!(close(Interval::new(-4.0, -1.0).root(Interval::new_single(2.0)), &[]));
+        encloses(Interval::new(-4.0, 9.0).root(Interval::new_single(2.0)), Interval::new(-4.0, 9.0), f64::sqrt);
+    }
+
+    #[test]
+    fn pieces_merge_a_third() {
+        let pieces = Pieces::two(Interval::new(0.0, 1.0), Interval::new(5.0, 6.0)).push(Interval::new(2.0, 3.0)).push(Interval::new_single(f64::NAN));
+        assert!(close(pieces, &[(0.0, 1.0), (2.0, 6.0)]));
+    }
+}
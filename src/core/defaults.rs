@@ -12,9 +12,40 @@ pub const SIZE       : data::Vector2 = data::Vector2 {
     y : 100
 };
 
+// Axes of the resolution left at zero are worked out from the frame's size on the bed at `DPI`.
 pub const RESOLUTION : data::Vector2 = data::Vector2 {
     x : 0,
     y : 0
 };
+// One pixel per millimetre.
+pub const DPI        : f64           = 25.4;
 
 pub const EXPORT     : &'static str  = "export.png";
+
+pub const DIVISION_BY_ZERO : data::ExceptionLevel = data::ExceptionLevel::Warning;
+
+pub const THICKNESS  : f64           = 0.0;
+
+pub const UNITS      : data::Unit    = data::Unit::Millimetre;
+
+// Laser power as a percentage of full power, and feed rate in millimetres per minute.
+pub const POWER      : f64           = 50.0;
+pub const SPEED      : f64           = 1000.0;
+pub const PASSES     : i32           = 1;
+
+// Distance run past each end of a raster row, in millimetres.
+pub const OVERSCAN   : f64           = 2.0;
+
+// Distance paths may be moved when simplifying and fitting them, in units. Zero keeps every traced point.
+pub const TOLERANCE  : f64           = 0.0;
+
+pub const ORDERING   : data::Ordering = data::Ordering::Script;
+
+// Kerf of the paths of each layer, until a `#kerf` for the layer. Zero leaves paths where they are traced.
+pub const KERF       : data::Kerf     = data::Kerf {width : 0.0, join : data::Join::Miter};
+
+// Hatch of the paths that follow, until the next `#hatch`. Zero spacing leaves paths unfilled.
+pub const HATCH      : data::Hatch    = data::Hatch {spacing : 0.0, angle : 0.0, cross : false, alternate : false, outline : false};
+
+// Style of an equation without attributes, drawn like the rest of its layer.
+pub const STYLE      : data::Style    = data::Style {colour : None, width : None, dash : Vec::new()};
@@ -0,0 +1,262 @@
+use super::data;
+use super::interpreter;
+use super::paths;
+use super::renderer;
+use super::exporter;
+use super::layers;
+use super::style;
+
+
+
+// Function for writing paths as an R12 ASCII DXF drawing.
+// Positions are in `#units` units on the bed. Every path becomes a polyline, on the DXF layer of its `#layer` in the layer's colour,
+// or on a DXF layer of its own for equations without one. The colour, width and dashes of an equation's style are set on its polylines.
+pub fn export(data : &interpreter::InterpreterData, paths : &paths::Paths) -> renderer::RendererResult {
+    return exporter::write(&data.export, document(data, paths));
+}
+
+
+
+// Build the drawing written by `export`.
+pub fn document(data : &interpreter::InterpreterData, paths : &paths::Paths) -> String {
+    let mut res = vec![];
+
+    // Header, with the drawing units and extents. R12 has no units variable of its own, so the `#units` are given
+    // both as the insertion units and as the measurement system, which programs reading R12 drawings look for.
+    section(&mut res, "HEADER");
+    group(&mut res, 9, "$ACADVER");
+    group(&mut res, 1, "AC1009");
+    group(&mut res, 9, "$INSUNITS");
+    group(&mut res, 70, match data.units {
+        data::Unit::Millimetre => "4",
+        data::Unit::Inch       => "1"
+    });
+    group(&mut res, 9, "$MEASUREMENT");
+    group(&mut res, 70, match data.units {
+        data::Unit::Millimetre => "1",
+        data::Unit::Inch       => "0"
+    });
+    for (name, point) in [("$EXTMIN", data::Point {x : paths.frame.x, y : paths.frame.y}), ("$EXTMAX", data::Point {x : paths.frame.x + paths.frame.w, y : paths.frame.y + paths.frame.h})] {
+        group(&mut res, 9, name);
+        group(&mut res, 10, &exporter::number(point.x, 6));
+        group(&mut res, 20, &exporter::number(point.y, 6));
+        group(&mut res, 30, "0");
+    }
+    group(&mut res, 0, "ENDSEC");
+
+    // Tables, with the line types and the layers used by entities.
+    section(&mut res, "TABLES");
+    group(&mut res, 0, "TABLE");
+    group(&mut res, 2, "LTYPE");
+    let dashed = (0..paths.equations.len()).filter(|index| ! data.styles[*index].dash.is_empty()).collect::<Vec<usize>>();
+    group(&mut res, 70, &(dashed.len() + 1).to_string());
+    group(&mut res, 0, "LTYPE");
+    group(&mut res, 2, "CONTINUOUS");
+    group(&mut res, 70, "0");
+    group(&mut res, 3, "Solid line");
+    group(&mut res, 72, "65");
+    group(&mut res, 73, "0");
+    group(&mut res, 40, "0");
+    // Each dashed equation has a line type of its own, with gaps given as negative lengths.
+    // A pattern with an odd number of lengths is used twice over, as the dashes and gaps swap the second time.
+    for index in dashed.iter() {
+        let mut lengths = data.styles[*index].dash.clone();
+        if lengths.len() % 2 == 1 {
+            lengths.extend_from_within(..);
+        }
+        group(&mut res, 0, "LTYPE");
+        group(&mut res, 2, &line_type(*index));
+        group(&mut res, 70, "0");
+        group(&mut res, 3, "Dashed line");
+        group(&mut res, 72, "65");
+        group(&mut res, 73, &lengths.len().to_string());
+        group(&mut res, 40, &exporter::number(lengths.iter().sum(), 6));
+        for (place, length) in lengths.iter().enumerate() {
+            group(&mut res, 49, &exporter::number(if place % 2 == 0 { *length } else { - length }, 6));
+        }
+    }
+    group(&mut res, 0, "ENDTAB");
+    group(&mut res, 0, "TABLE");
+    group(&mut res, 2, "LAYER");
+    // Equations on the same `#layer` share a DXF layer.
+    let layer_names = layer_names(data, paths.equations.len());
+    let mut names   = vec![];
+    for (index, name) in layer_names.iter().enumerate() {
+        if ! names.iter().any(|(other, _)| other == name) {
+            names.push((name.clone(), layers::colour(data, index).1));
+        }
+    }
+    group(&mut res, 70, &names.len().to_string());
+    for (name, colour) in names.iter() {
+        group(&mut res, 0, "LAYER");
+        group(&mut res, 2, name);
+        group(&mut res, 70, "0");
+        group(&mut res, 62, &colour.to_string());
+        group(&mut res, 6, "CONTINUOUS");
+    }
+    group(&mut res, 0, "ENDTAB");
+    group(&mut res, 0, "ENDSEC");
+
+    // Entities, one polyline per path.
+    section(&mut res, "ENTITIES");
+    for path in paths.paths.iter() {
+        let layer  = &layer_names[path.equation];
+        let style  = &data.styles[path.equation];
+        let dashed = ! style.dash.is_empty();
+        group(&mut res, 0, "POLYLINE");
+        group(&mut res, 8, layer);
+        if dashed {
+            group(&mut res, 6, &line_type(path.equation));
+        }
+        if style.colour.is_some() {
+            group(&mut res, 62, &style::colour(data, path.equation).1.to_string());
+        }
+        group(&mut res, 66, "1");
+        group(&mut res, 10, "0");
+        group(&mut res, 20, "0");
+        group(&mut res, 30, "0");
+        if let Some(width) = style.width {
+            group(&mut res, 40, &exporter::number(width, 6));
+            group(&mut res, 41, &exporter::number(width, 6));
+        }
+        // Flag 1 closes the polyline, and flag 128 runs dashes on around its vertices rather than starting them again at each.
+        group(&mut res, 70, &((if path.closed { 1 } else { 0 }) + (if dashed { 128 } else { 0 })).to_string());
+        // A closed polyline joins its last vertex to its first, so the repeated point is left out.
+        let count = if path.closed { path.points.len() - 1 } else { path.points.len() };
+        for point in path.points[..count].iter() {
+            group(&mut res, 0, "VERTEX");
+            group(&mut res, 8, layer);
+            group(&mut res, 10, &exporter::number(point.x, 6));
+            group(&mut res, 20, &exporter::number(point.y, 6));
+            group(&mut res, 30, "0");
+        }
+        group(&mut res, 0, "SEQEND");
+        group(&mut res, 8, layer);
+    }
+    group(&mut res, 0, "ENDSEC");
+    group(&mut res, 0, "EOF");
+
+    return res.join("\n") + "\n";
+}
+
+
+
+// Names of the layers holding each of the first `count` equations' paths. DXF layer names can not hold most symbols,
+// so the source is not used, and symbols in `#layer` names are replaced. As layer names are not case sensitive either,
+// names made the same this way are told apart by a number on the end, in the order the layers were made.
+fn layer_names(data : &interpreter::InterpreterData, count : usize) -> Vec<String> {
+    let mut taken : Vec<String> = vec![];
+    let mut unique = |name : String| {
+        let mut res    = name.clone();
+        let mut number = 1;
+        while taken.contains(&res) {
+            number += 1;
+            res     = format!("{}_{}", name, number);
+        }
+        taken.push(res.clone());
+        return res;
+    };
+    let names = data.layers.iter().map(|layer| unique(layer.name.to_uppercase().chars().map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '$' { c } else { '_' }).collect())).collect::<Vec<String>>();
+    return (0..count).map(|equation| match data.equation_layers[equation] {
+        Some(index) => names[index].clone(),
+        None        => unique(format!("EQUATION_{}", equation))
+    }).collect();
+}
+
+
+
+// Name of the line type of a dashed equation.
+fn line_type(equation : usize) -> String {
+    return format!("DASHED_{}", equation);
+}
+
+
+
+// Start a section.
+fn section(res : &mut Vec<String>, name : &str) {
+    group(res, 0, "SECTION");
+    group(res, 2, name);
+}
+
+
+
+// Add a group code and its value.
+fn group(res : &mut Vec<String>, code : i32, value : &str) {
+    res.push(format!("{:>3}", code));
+    res.push(value.to_string());
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::run;
+
+    // A polyline read back from a drawing, with its layer, closed flag and vertices.
+    type Polyline = (String, bool, Vec<data::Point>);
+
+    // Read the layer table and the polylines of a drawing.
+    fn parse(text : &str) -> (Vec<String>, Vec<Polyline>) {
+        let lines  = text.lines().collect::<Vec<&str>>();
+        let groups = lines.chunks(2).map(|pair| (pair[0].trim().parse::<i32>().unwrap(), pair[1])).collect::<Vec<(i32, &str)>>();
+        let mut layers    = vec![];
+        let mut polylines : Vec<Polyline> = vec![];
+        let mut entity    = "";
+        for (code, value) in groups {
+            match (code, entity) {
+                (0, _)               => {
+                    entity = value;
+                    match value {
+                        "POLYLINE" => polylines.push(("".to_string(), false, vec![])),
+                        "VERTEX"   => polylines.last_mut().unwrap().2.push(data::Point {x : 0.0, y : 0.0}),
+                        _          => ()
+                    }
+                },
+                (2, "LAYER")         => layers.push(value.to_string()),
+                (8, "POLYLINE")      => polylines.last_mut().unwrap().0 = value.to_string(),
+                (70, "POLYLINE")     => polylines.last_mut().unwrap().1 = value.parse::<i32>().unwrap() & 1 == 1,
+                (10, "VERTEX")       => polylines.last_mut().unwrap().2.last_mut().unwrap().x = value.parse().unwrap(),
+                (20, "VERTEX")       => polylines.last_mut().unwrap().2.last_mut().unwrap().y = value.parse().unwrap(),
+                _                    => ()
+            }
+        }
+        return (layers, polylines);
+    }
+
+    // Read the value of a header variable.
+    fn header(text : &str, name : &str) -> String {
+        let lines = text.lines().collect::<Vec<&str>>();
+        let at    = lines.iter().position(|line| *line == name).unwrap();
+        return lines[at + 2].to_string();
+    }
+
+    #[test]
+    fn inch_units() {
+        let (data, paths) = run::trace_text("#units(\"in\")\n#frame(-5, -5, 10, 10)\ny = x\n", 1);
+        let text          = document(&data, &paths);
+        assert_eq!(header(&text, "$INSUNITS"), "1");
+        assert_eq!(header(&text, "$MEASUREMENT"), "0");
+    }
+
+    #[test]
+    fn round_trip() {
+        let (data, paths) = run::trace_text("#frame(-5, -5, 10, 10)\ny = x\n#layer(\"a b\", \"cut\", 80, 100, 1)\nx * x + y * y = 9\n#layer(\"a_b\", \"score\", 20, 500, 1)\ny = 2\n#layer(\"A b\", \"engrave\", 10, 1000, 1)\ny = 3\n", 1);
+        let text          = document(&data, &paths);
+
+        assert_eq!(header(&text, "$INSUNITS"), "4");
+        assert_eq!(header(&text, "$MEASUREMENT"), "1");
+        let (layers, polylines) = parse(&text);
+        assert_eq!(layers, vec!["EQUATION_0", "A_B", "A_B_2", "A_B_3"]);
+        assert_eq!(polylines.len(), paths.paths.len());
+        for (path, (layer, closed, points)) in paths.paths.iter().zip(polylines.iter()) {
+            assert_eq!(*layer, layer_names(&data, paths.equations.len())[path.equation]);
+            assert_eq!(*closed, path.closed);
+            let count = if path.closed { path.points.len() - 1 } else { path.points.len() };
+            assert_eq!(points.len(), count);
+            for (point, read) in path.points.iter().zip(points.iter()) {
+                assert!((point.x - read.x).abs() <= 1e-6 && (point.y - read.y).abs() <= 1e-6, "{:?} was read back as {:?}.", point, read);
+            }
+        }
+    }
+}
@@ -10,6 +10,8 @@ use super::data;
 pub struct LexerException {
     pub base    : LexerExceptionBase,
     pub message : String,
+    // Where the error is in the script, which is not shown yet.
+    #[allow(dead_code)]
     pub range   : data::Range
 }
 impl fmt::Display for LexerException {
@@ -45,6 +47,8 @@ pub enum LexerExceptionBase {
 pub struct ParserException {
     pub base    : ParserExceptionBase,
     pub message : String,
+    // Where the error is in the script, which is not shown yet.
+    #[allow(dead_code)]
     pub range   : data::Range
 }
 impl fmt::Display for ParserException {
@@ -80,6 +84,8 @@ pub enum ParserExceptionBase {
 pub struct InterpreterException {
     pub base    : InterpreterExceptionBase,
     pub message : String,
+    // Where the error is in the script, which is not shown yet.
+    #[allow(dead_code)]
     pub range   : data::Range
 }
 impl fmt::Display for InterpreterException {
@@ -119,6 +125,7 @@ impl fmt::Display for RendererException {
             RendererExceptionBase::NoException              => "NoException".normal(),
             RendererExceptionBase::DivisionByZeroException  => "DivisionByZeroException".normal(),
             RendererExceptionBase::InvalidVariableException => "InvalidVariableException".normal(),
+            RendererExceptionBase::TooManyBranchesException => "TooManyBranchesException".normal(),
             RendererExceptionBase::InternalException        => "InternalException".on_bright_red().white().bold()
         }.to_string();
 
@@ -135,6 +142,7 @@ pub enum RendererExceptionBase {
 
     DivisionByZeroException,
     InvalidVariableException,
+    TooManyBranchesException,
     InternalException
 }
 
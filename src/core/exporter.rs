@@ -0,0 +1,117 @@
+use std::fs;
+
+use super::data;
+use super::exceptions;
+use super::interpreter;
+use super::paths;
+use super::renderer;
+use super::svg;
+use super::dxf;
+use super::gcode;
+use super::hpgl;
+use super::page;
+use super::lightburn;
+
+
+
+// Function for writing paths to the export file, in the format picked by its extension.
+// Unknown extensions are rendered as a PNG image.
+pub fn export(data : interpreter::InterpreterData, paths : &paths::Paths, threads : usize) -> renderer::RendererResult {
+    return match extension(&data.export).as_str() {
+        "json"                => export_json(&data, paths),
+        "svg"                 => svg::export(&data, paths),
+        "dxf"                 => dxf::export(&data, paths),
+        "gcode" | "nc" | "gc" => gcode::export(&data, paths, threads),
+        "plt" | "hpgl"        => hpgl::export(&data, paths),
+        "pdf"                 => page::export_pdf(&data, paths),
+        "eps" | "ps"          => page::export_eps(&data, paths),
+        "lbrn2" | "lbrn"      => lightburn::export(&data, paths),
+        _                     => renderer::render(data, paths, threads)
+    };
+}
+
+
+
+// Get the lowercase extension of a filename, or an empty string if it has none.
+pub fn extension(filename : &str) -> String {
+    return match filename.rsplit_once('.') {
+        Some((_, extension)) if ! extension.contains('/') => extension.to_lowercase(),
+        _                                                  => "".to_string()
+    };
+}
+
+
+
+// Write the paths themselves, so other tools can read them without going through an image.
+fn export_json(data : &interpreter::InterpreterData, paths : &paths::Paths) -> renderer::RendererResult {
+    return write(&data.export, paths.to_json());
+}
+
+
+
+// Format a number for a text export, with at most `decimals` decimal places and no trailing zeros.
+pub fn number(value : f64, decimals : usize) -> String {
+    let res = format!("{:.*}", decimals, value);
+    let res = if res.contains('.') { res.trim_end_matches('0').trim_end_matches('.') } else { res.as_str() };
+    return if res == "-0" { "0".to_string() } else { res.to_string() };
+}
+
+
+
+// Escape text for use in XML content or attributes.
+pub fn escape_xml(text : &str) -> String {
+    return text
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;");
+}
+
+
+
+// Write text to the export file.
+pub fn write(filename : &str, contents : String) -> renderer::RendererResult {
+    return match fs::write(filename, contents) {
+        Ok(_v) => success(filename.to_string()),
+        Err(e) => failure(format!("Could not write `{}`: {}", data::escapify(filename.to_string()), e))
+    };
+}
+
+
+
+// Build a successful result for a written export file.
+pub fn success(filename : String) -> renderer::RendererResult {
+    return renderer::RendererResult {
+        success         : true,
+        export_filename : filename,
+        exception       : exceptions::RendererException {
+            base    : exceptions::RendererExceptionBase::NoException,
+            message : "".to_string(),
+            range   : data::Range {
+                filename : "".to_string(),
+                start    : 0,
+                end      : 0
+            }
+        }
+    };
+}
+
+
+
+// Build a failed result for an export file that could not be written.
+pub fn failure(message : String) -> renderer::RendererResult {
+    return renderer::RendererResult {
+        success         : false,
+        export_filename : "".to_string(),
+        exception       : exceptions::RendererException {
+            base    : exceptions::RendererExceptionBase::InternalException,
+            message : message,
+            range   : data::Range {
+                filename : "".to_string(),
+                start    : 0,
+                end      : 0
+            }
+        }
+    };
+}
@@ -0,0 +1,475 @@
+use std::f64::consts::PI;
+
+use super::data;
+use super::interpreter;
+use super::paths;
+
+
+
+// Piece of a fitted path, starting where the previous piece ends.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Segment {
+    Line {
+        to : data::Point
+    },
+    // Circular arc around `centre`, turning clockwise if `clockwise` is set.
+    Arc {
+        to        : data::Point,
+        centre    : data::Point,
+        clockwise : bool
+    },
+    Cubic {
+        control1 : data::Point,
+        control2 : data::Point,
+        to       : data::Point
+    }
+}
+impl Segment {
+    // Move every point of the segment. `f` must not mirror, as arcs keep their direction.
+    pub fn map<F : Fn(data::Point) -> data::Point>(&self, f : F) -> Segment {
+        return match *self {
+            Segment::Line  {to}                     => Segment::Line {to : f(to)},
+            Segment::Arc   {to, centre, clockwise}  => Segment::Arc {to : f(to), centre : f(centre), clockwise : clockwise},
+            Segment::Cubic {control1, control2, to} => Segment::Cubic {control1 : f(control1), control2 : f(control2), to : f(to)}
+        };
+    }
+}
+
+
+
+// Get the tolerance for each fitting stage. Paths are simplified and then fitted, each within half of the `#tolerance`,
+// so fitted curves are always within the tolerance of the traced curve.
+pub fn stage_tolerance(data : &interpreter::InterpreterData) -> f64 {
+    return data.tolerance / 2.0;
+}
+
+
+
+// Simplify a polyline with the Ramer–Douglas–Peucker algorithm, keeping every point of the original within `tolerance`
+// of the result. The ends are always kept.
+pub fn simplify(points : &[data::Point], tolerance : f64) -> Vec<data::Point> {
+    if points.len() < 3 || tolerance <= 0.0 {
+        return points.to_vec();
+    }
+    let mut keep  = vec![false; points.len()];
+    keep[0]                 = true;
+    keep[points.len() - 1]  = true;
+    let mut stack = vec![(0, points.len() - 1)];
+    while let Some((start, end)) = stack.pop() {
+        let mut furthest = (0.0, 0);
+        for index in (start + 1)..end {
+            let distance = paths::point_segment_distance(points[index], points[start], points[end]);
+            if distance > furthest.0 {
+                furthest = (distance, index);
+            }
+        }
+        if furthest.0 > tolerance {
+            keep[furthest.1] = true;
+            stack.push((start, furthest.1));
+            stack.push((furthest.1, end));
+        }
+    }
+    return points.iter().zip(keep).filter(|(_, keep)| *keep).map(|(point, _)| *point).collect();
+}
+
+
+
+// Get the segments of a polyline as straight lines.
+pub fn lines(points : &[data::Point]) -> Vec<Segment> {
+    return points.iter().skip(1).map(|point| Segment::Line {to : *point}).collect();
+}
+
+
+
+// Replace runs of a polyline with circular arcs where they stay within `tolerance` of it.
+// Runs are grown greedily from the start, and points which fit no arc are joined by lines.
+pub fn fit_arcs(points : &[data::Point], tolerance : f64) -> Vec<Segment> {
+    if tolerance <= 0.0 {
+        return lines(points);
+    }
+    let mut res   = vec![];
+    let mut start = 0;
+    while start + 1 < points.len() {
+        let mut best = None;
+        let mut end  = start + 2;
+        while end < points.len() {
+            match fit_arc(&points[start..=end], tolerance) {
+                Some(arc) => best = Some((end, arc)),
+                None      => break
+            }
+            end += 1;
+        }
+        match best {
+            Some((end, (centre, clockwise))) => {
+                res.push(Segment::Arc {
+                    to        : points[end],
+                    centre    : centre,
+                    clockwise : clockwise
+                });
+                start = end;
+            },
+            None                             => {
+                res.push(Segment::Line {to : points[start + 1]});
+                start += 1;
+            }
+        }
+    }
+    return res;
+}
+
+
+
+// Find the arc through the ends and middle of a run of points, returning its centre and direction
+// if it sweeps the points in order and every segment of the run is within `tolerance` of it.
+fn fit_arc(points : &[data::Point], tolerance : f64) -> Option<(data::Point, bool)> {
+    let first  = points[0];
+    let last   = points[points.len() - 1];
+    let centre = circle(first, points[points.len() / 2], last)?;
+    let radius = distance(centre, first);
+    // Nearly straight runs give huge circles which lose precision, and are better drawn as lines.
+    if radius > data::FITTING_MAX_ARC_RATIO * distance(first, last) {
+        return None;
+    }
+    let turn      = cross(sub(points[points.len() / 2], first), sub(last, first));
+    let clockwise = turn < 0.0;
+
+    let mut sweep = 0.0;
+    for pair in points.windows(2) {
+        let step = angle(sub(pair[1], centre)) - angle(sub(pair[0], centre));
+        let step = if step > PI { step - 2.0 * PI } else if step <= -PI { step + 2.0 * PI } else { step };
+        if (clockwise && step > 0.0) || (! clockwise && step < 0.0) {
+            return None;
+        }
+        sweep += step.abs();
+        // The segment is within the radial error of its ends plus the sagitta of the arc over it.
+        let half     = distance(pair[0], pair[1]) / 2.0;
+        let sagitta  = radius - (radius * radius - half * half).max(0.0).sqrt();
+        let radial   = (distance(pair[0], centre) - radius).abs().max((distance(pair[1], centre) - radius).abs());
+        if radial + sagitta > tolerance {
+            return None;
+        }
+    }
+    if sweep >= 2.0 * PI {
+        return None;
+    }
+    return Some((centre, clockwise));
+}
+
+
+
+// Replace a polyline with cubic Bézier curves within `tolerance` of it, splitting it where a single curve does not fit.
+// Based on Schneider's algorithm from Graphics Gems.
+pub fn fit_cubics(points : &[data::Point], tolerance : f64) -> Vec<Segment> {
+    if tolerance <= 0.0 || points.len() < 3 {
+        return lines(points);
+    }
+    let mut res = vec![];
+    let start   = unit(sub(points[1], points[0]));
+    let end     = unit(sub(points[points.len() - 2], points[points.len() - 1]));
+    fit_cubic(points, start, end, tolerance, &mut res);
+    return res;
+}
+
+
+
+// Fit curves to a run of points leaving along the tangent `start` and arriving back along `end`.
+fn fit_cubic(points : &[data::Point], start : data::Point, end : data::Point, tolerance : f64, res : &mut Vec<Segment>) {
+    if points.len() == 2 {
+        res.push(Segment::Line {to : points[1]});
+        return;
+    }
+
+    let mut parameters   = chord_parameters(points);
+    let mut curve        = bezier(points, &parameters, start, end);
+    let (mut error, mut split) = cubic_error(points, &parameters, &curve);
+    if error <= tolerance {
+        res.push(curve_segment(&curve));
+        return;
+    }
+    // A curve which is close may fit once the points are matched to better places along it.
+    if error <= tolerance * 4.0 {
+        for _ in 0..data::FITTING_ITERATIONS {
+            parameters = reparameterise(points, &parameters, &curve);
+            curve      = bezier(points, &parameters, start, end);
+            (error, split) = cubic_error(points, &parameters, &curve);
+            if error <= tolerance {
+                res.push(curve_segment(&curve));
+                return;
+            }
+        }
+    }
+
+    // Split at the worst point, keeping the curve smooth through it.
+    let mut middle = unit(sub(points[split - 1], points[split + 1]));
+    if middle.x == 0.0 && middle.y == 0.0 {
+        middle = unit(sub(points[split - 1], points[split]));
+    }
+    fit_cubic(&points[..=split], start, middle, tolerance, res);
+    fit_cubic(&points[split..], data::Point {x : -middle.x, y : -middle.y}, end, tolerance, res);
+}
+
+
+
+// Place each point along the curve by its share of the polyline's length.
+fn chord_parameters(points : &[data::Point]) -> Vec<f64> {
+    let mut res = vec![0.0];
+    for pair in points.windows(2) {
+        res.push(res[res.len() - 1] + distance(pair[0], pair[1]));
+    }
+    let total = res[res.len() - 1];
+    return res.iter().map(|length| if total > 0.0 { length / total } else { 0.0 }).collect();
+}
+
+
+
+// Find the curve between the ends of a run, with control points along the end tangents, best fitting the points by least squares.
+fn bezier(points : &[data::Point], parameters : &[f64], start : data::Point, end : data::Point) -> [data::Point; 4] {
+    let first = points[0];
+    let last  = points[points.len() - 1];
+    let mut c = [[0.0, 0.0], [0.0, 0.0]];
+    let mut x = [0.0, 0.0];
+    for (point, t) in points.iter().zip(parameters) {
+        let b  = bernstein(*t);
+        let a0 = scale(start, b[1]);
+        let a1 = scale(end, b[2]);
+        c[0][0] += dot(a0, a0);
+        c[0][1] += dot(a0, a1);
+        c[1][1] += dot(a1, a1);
+        let rest = sub(*point, add(scale(first, b[0] + b[1]), scale(last, b[2] + b[3])));
+        x[0] += dot(a0, rest);
+        x[1] += dot(a1, rest);
+    }
+    c[1][0] = c[0][1];
+
+    let determinant = c[0][0] * c[1][1] - c[1][0] * c[0][1];
+    let length      = distance(first, last);
+    let (mut left, mut right) = if determinant != 0.0 {
+        ((x[0] * c[1][1] - x[1] * c[0][1]) / determinant, (c[0][0] * x[1] - c[1][0] * x[0]) / determinant)
+    } else {
+        (0.0, 0.0)
+    };
+    // Control points behind or on the ends give loops or cusps, so fall back to a third of the way along.
+    if left < length * 1e-6 || right < length * 1e-6 {
+        left  = length / 3.0;
+        right = length / 3.0;
+    }
+    return [first, add(first, scale(start, left)), add(last, scale(end, right)), last];
+}
+
+
+
+// Get the furthest the curve strays from the polyline, and the inner point to split at if it is too far.
+// Both each point's distance from its place on the curve and each segment's distance from the curve between them count.
+fn cubic_error(points : &[data::Point], parameters : &[f64], curve : &[data::Point; 4]) -> (f64, usize) {
+    let mut error = 0.0;
+    let mut split = points.len() / 2;
+    for index in 0..points.len() {
+        let at = distance(evaluate(curve, parameters[index]), points[index]);
+        if at > error && index > 0 && index < points.len() - 1 {
+            error = at;
+            split = index;
+        }
+        if index + 1 < points.len() {
+            for check in 1..data::FITTING_CHECKS {
+                let t       = parameters[index] + (parameters[index + 1] - parameters[index]) * check as f64 / data::FITTING_CHECKS as f64;
+                let between = paths::point_segment_distance(evaluate(curve, t), points[index], points[index + 1]);
+                if between > error {
+                    error = between;
+                    split = (index + 1).clamp(1, points.len() - 2);
+                }
+            }
+        }
+    }
+    return (error, split);
+}
+
+
+
+// Move each point's place along the curve closer to the nearest point of the curve with a Newton step.
+fn reparameterise(points : &[data::Point], parameters : &[f64], curve : &[data::Point; 4]) -> Vec<f64> {
+    let first  = [
+        scale(sub(curve[1], curve[0]), 3.0),
+        scale(sub(curve[2], curve[1]), 3.0),
+        scale(sub(curve[3], curve[2]), 3.0)
+    ];
+    let second = [
+        scale(sub(first[1], first[0]), 2.0),
+        scale(sub(first[2], first[1]), 2.0)
+    ];
+    return points.iter().zip(parameters).map(|(point, t)| {
+        let t           = *t;
+        let offset      = sub(evaluate(curve, t), *point);
+        let d1          = add(add(scale(first[0], (1.0 - t) * (1.0 - t)), scale(first[1], 2.0 * t * (1.0 - t))), scale(first[2], t * t));
+        let d2          = add(scale(second[0], 1.0 - t), scale(second[1], t));
+        let denominator = dot(d1, d1) + dot(offset, d2);
+        if denominator == 0.0 {
+            return t;
+        }
+        return (t - dot(offset, d1) / denominator).clamp(0.0, 1.0);
+    }).collect();
+}
+
+
+
+// Turn curve points into a segment.
+fn curve_segment(curve : &[data::Point; 4]) -> Segment {
+    return Segment::Cubic {
+        control1 : curve[1],
+        control2 : curve[2],
+        to       : curve[3]
+    };
+}
+
+
+
+// Get the point part of the way along a cubic curve.
+fn evaluate(curve : &[data::Point; 4], t : f64) -> data::Point {
+    let b = bernstein(t);
+    return add(add(scale(curve[0], b[0]), scale(curve[1], b[1])), add(scale(curve[2], b[2]), scale(curve[3], b[3])));
+}
+
+
+
+// Weights of the points of a cubic curve part of the way along it.
+fn bernstein(t : f64) -> [f64; 4] {
+    let s = 1.0 - t;
+    return [s * s * s, 3.0 * s * s * t, 3.0 * s * t * t, t * t * t];
+}
+
+
+
+// Find the centre of the circle through three points, or none if they are in a line.
+fn circle(a : data::Point, b : data::Point, c : data::Point) -> Option<data::Point> {
+    // Work relative to the first point to keep precision.
+    let b = sub(b, a);
+    let c = sub(c, a);
+    let d = 2.0 * cross(b, c);
+    if d.abs() < 1e-12 * dot(b, b).max(dot(c, c)) {
+        return None;
+    }
+    let bb = dot(b, b);
+    let cc = dot(c, c);
+    return Some(data::Point {
+        x : a.x + (c.y * bb - b.y * cc) / d,
+        y : a.y + (b.x * cc - c.x * bb) / d
+    });
+}
+
+
+
+pub fn add(a : data::Point, b : data::Point) -> data::Point {
+    return data::Point {x : a.x + b.x, y : a.y + b.y};
+}
+
+pub fn sub(a : data::Point, b : data::Point) -> data::Point {
+    return data::Point {x : a.x - b.x, y : a.y - b.y};
+}
+
+pub fn scale(a : data::Point, factor : f64) -> data::Point {
+    return data::Point {x : a.x * factor, y : a.y * factor};
+}
+
+pub fn dot(a : data::Point, b : data::Point) -> f64 {
+    return a.x * b.x + a.y * b.y;
+}
+
+pub fn cross(a : data::Point, b : data::Point) -> f64 {
+    return a.x * b.y - a.y * b.x;
+}
+
+pub fn distance(a : data::Point, b : data::Point) -> f64 {
+    return (a.x - b.x).hypot(a.y - b.y);
+}
+
+fn angle(a : data::Point) -> f64 {
+    return a.y.atan2(a.x);
+}
+
+pub fn unit(a : data::Point) -> data::Point {
+    let length = a.x.hypot(a.y);
+    if length == 0.0 {
+        return a;
+    }
+    return scale(a, 1.0 / length);
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::run;
+
+    // Points along the segments fitted from `start`, close enough together that the polyline through them
+    // is within a tiny fraction of the tolerance of the segments.
+    fn flatten(start : data::Point, segments : &[Segment]) -> Vec<data::Point> {
+        let mut res  = vec![start];
+        let mut from = start;
+        for segment in segments.iter() {
+            match *segment {
+                Segment::Line  {to}                     => res.push(to),
+                Segment::Arc   {to, centre, clockwise}  => {
+                    let radius = distance(centre, from);
+                    let first  = angle(sub(from, centre));
+                    let mut sweep = angle(sub(to, centre)) - first;
+                    if clockwise && sweep > 0.0 {
+                        sweep -= 2.0 * PI;
+                    } else if ! clockwise && sweep < 0.0 {
+                        sweep += 2.0 * PI;
+                    }
+                    for step in 1..=256 {
+                        let at = first + sweep * step as f64 / 256.0;
+                        res.push(data::Point {x : centre.x + radius * at.cos(), y : centre.y + radius * at.sin()});
+                    }
+                },
+                Segment::Cubic {control1, control2, to} => {
+                    let curve = [from, control1, control2, to];
+                    for step in 1..=256 {
+                        res.push(evaluate(&curve, step as f64 / 256.0));
+                    }
+                }
+            }
+            from = res[res.len() - 1];
+        }
+        return res;
+    }
+
+    // Furthest either polyline strays from the other.
+    fn deviation(a : &[data::Point], b : &[data::Point]) -> f64 {
+        let one_way = |a : &[data::Point], b : &[data::Point]| a.iter().map(|point| {
+            b.windows(2).map(|pair| paths::point_segment_distance(*point, pair[0], pair[1])).fold(f64::INFINITY, f64::min)
+        }).fold(0.0, f64::max);
+        return one_way(a, b).max(one_way(b, a));
+    }
+
+    // Circles, including a closed one, runs which are nearly straight, and a wave which turns both ways.
+    fn inputs() -> Vec<Vec<data::Point>> {
+        let along = |count : usize, f : &dyn Fn(f64) -> data::Point| (0..=count).map(|step| f(step as f64 / count as f64)).collect::<Vec<data::Point>>();
+        return vec![
+            along(400, &|t| data::Point {x : 10.0 * (2.0 * PI * t).cos(), y : 10.0 * (2.0 * PI * t).sin()}),
+            along(50, &|t| data::Point {x : 3.0 + 0.5 * (PI * t).cos(), y : -1.0 - 0.5 * (PI * t).sin()}),
+            along(300, &|t| data::Point {x : 100.0 * t, y : 0.001 * (10.0 * t).sin()}),
+            along(300, &|t| data::Point {x : 100.0 * t, y : 0.01 * (100.0 * t) * (100.0 * t) / 100.0}),
+            along(500, &|t| data::Point {x : 40.0 * t, y : 5.0 * (t * 12.0).sin()})
+        ];
+    }
+
+    #[test]
+    fn fits_stay_within_tolerance() {
+        for script in ["#tolerance(0.01)\ny = x\n", "#tolerance(0.1)\ny = x\n", "#tolerance(1)\ny = x\n"] {
+            let tolerance = stage_tolerance(&run::interpret_text(script));
+            for (index, points) in inputs().iter().enumerate() {
+                // A little is allowed for rounding and for the flattened curves.
+                let allowed    = tolerance * 1.001;
+                let simplified = simplify(points, tolerance);
+                assert!(deviation(points, &simplified) <= allowed, "Simplifying input {} strays {} at {}.", index, deviation(points, &simplified), tolerance);
+                let arcs       = flatten(simplified[0], &fit_arcs(&simplified, tolerance));
+                assert!(deviation(&simplified, &arcs) <= allowed, "Arcs fitted to input {} stray {} at {}.", index, deviation(&simplified, &arcs), tolerance);
+                assert!(deviation(points, &arcs) <= 2.0 * allowed);
+                let cubics     = flatten(simplified[0], &fit_cubics(&simplified, tolerance));
+                assert!(deviation(&simplified, &cubics) <= allowed, "Cubics fitted to input {} stray {} at {}.", index, deviation(&simplified, &cubics), tolerance);
+                assert!(deviation(points, &cubics) <= 2.0 * allowed);
+            }
+        }
+    }
+}
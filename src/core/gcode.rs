@@ -0,0 +1,370 @@
+use super::data;
+use super::interpreter;
+use super::paths;
+use super::renderer;
+use super::exporter;
+use super::fitting;
+use super::layers;
+use super::style;
+
+
+
+// G-code program being written for a GRBL controller in laser mode.
+// Positions are in `#units` units, measured from the origin.
+pub struct Writer {
+    pub lines    : Vec<String>,
+    pub origin   : data::Point,
+    pub decimals : usize,
+    // Spindle value giving full power.
+    max_spindle  : f64,
+    // Whether the laser is currently on and its spindle value, so they are only changed when needed.
+    laser        : bool,
+    spindle      : i64
+}
+impl Writer {
+    // Start a program for the machine profile if one is given, setting the units and absolute positioning.
+    pub fn new(data : &interpreter::InterpreterData, origin : data::Point) -> Writer {
+        let mut res = Writer {
+            lines       : vec![],
+            origin      : origin,
+            decimals    : match data.units {
+                data::Unit::Millimetre => 3,
+                data::Unit::Inch       => 4
+            },
+            max_spindle : match &data.machine {
+                Some(machine) => machine.max_spindle,
+                None          => data::GCODE_MAX_SPINDLE
+            },
+            laser       : false,
+            spindle     : 0
+        };
+        if let Some(machine) = &data.machine {
+            res.comment(format!("Machine `{}`.", machine.name));
+        }
+        res.line(match data.units {
+            data::Unit::Millimetre => "G21",
+            data::Unit::Inch       => "G20"
+        });
+        res.line("G90");
+        res.line("M5");
+        return res;
+    }
+
+    // Add a line of G-code.
+    pub fn line(&mut self, line : &str) {
+        self.lines.push(line.to_string());
+    }
+
+    // Add a comment.
+    pub fn comment(&mut self, text : String) {
+        self.lines.push(format!("; {}", text.replace('\n', " ")));
+    }
+
+    // Format a position on an axis, relative to the origin.
+    pub fn coordinate(&self, axis : char, value : f64) -> String {
+        let offset = if axis == 'X' { self.origin.x } else { self.origin.y };
+        return format!("{}{}", axis, exporter::number(value - offset, self.decimals));
+    }
+
+    // Move to a point with the laser off.
+    pub fn travel(&mut self, point : data::Point) {
+        self.laser_off();
+        let line = format!("G0 {} {}", self.coordinate('X', point.x), self.coordinate('Y', point.y));
+        self.lines.push(line);
+    }
+
+    // Move to a point with the laser on at `power` percent, at `speed` units per minute.
+    pub fn burn(&mut self, point : data::Point, power : f64, speed : f64) {
+        let line = format!("G1 {} {}", self.coordinate('X', point.x), self.coordinate('Y', point.y));
+        self.burn_line(line, power, speed);
+    }
+
+    // Move along an arc from `from` to `to` around `centre` with the laser on at `power` percent, at `speed` units per minute.
+    pub fn burn_arc(&mut self, from : data::Point, to : data::Point, centre : data::Point, clockwise : bool, power : f64, speed : f64) {
+        // The centre is given relative to the start of the arc.
+        let line = format!(
+            "{} {} {} I{} J{}",
+            if clockwise { "G2" } else { "G3" },
+            self.coordinate('X', to.x),
+            self.coordinate('Y', to.y),
+            exporter::number(centre.x - from.x, self.decimals),
+            exporter::number(centre.y - from.y, self.decimals)
+        );
+        self.burn_line(line, power, speed);
+    }
+
+    // Add a cutting move, switching the laser on first if it is off.
+    fn burn_line(&mut self, mut line : String, power : f64, speed : f64) {
+        if ! self.laser {
+            // Dynamic power mode scales the power with the speed, so corners are not burnt deeper.
+            self.spindle = self.spindle_for(power);
+            self.lines.push(format!("M4 S{}", self.spindle));
+            line += &format!(" F{}", exporter::number(speed, 1));
+            self.laser = true;
+        }
+        self.lines.push(line);
+    }
+
+    // Move along the current row to `x` with the laser on at a spindle value, at `speed` units per minute.
+    pub fn scan(&mut self, x : f64, spindle : i64, speed : f64) {
+        let mut line = format!("G1 {}", self.coordinate('X', x));
+        if ! self.laser {
+            self.spindle = 0;
+            self.lines.push("M4 S0".to_string());
+            line += &format!(" F{}", exporter::number(speed, 1));
+            self.laser = true;
+        }
+        if spindle != self.spindle {
+            line += &format!(" S{}", spindle);
+            self.spindle = spindle;
+        }
+        self.lines.push(line);
+    }
+
+    // Get the spindle value for a power percentage.
+    pub fn spindle_for(&self, power : f64) -> i64 {
+        return (power / 100.0 * self.max_spindle).round() as i64;
+    }
+
+    // Switch the laser off if it is on.
+    pub fn laser_off(&mut self) {
+        if self.laser {
+            self.lines.push("M5".to_string());
+            self.laser = false;
+        }
+    }
+
+    // End the program, returning to the origin with the laser off.
+    pub fn finish(mut self) -> String {
+        self.laser_off();
+        self.lines.push("G0 X0 Y0".to_string());
+        self.lines.push("M2".to_string());
+        return self.lines.join("\n") + "\n";
+    }
+}
+
+
+
+// Function for writing paths as G-code, with a block for each layer.
+// Engraved layers burn the rendered image of their paths, and other layers cut along the paths.
+// Layers are burnt engraved first and cut last, so parts are not moved by cutting them free.
+pub fn export(data : &interpreter::InterpreterData, paths : &paths::Paths, threads : usize) -> renderer::RendererResult {
+    return match document(data, paths, threads) {
+        Ok(document) => exporter::write(&data.export, document),
+        Err(e)       => exporter::failure(e)
+    };
+}
+
+
+
+// Build the program written by `export`.
+pub fn document(data : &interpreter::InterpreterData, paths : &paths::Paths, threads : usize) -> Result<String, String> {
+    let mut writer = Writer::new(data, origin(data));
+    let mut groups = layers::groups(data);
+    groups.sort_by_key(|group| group.layer.mode.priority());
+    for group in groups.iter() {
+        if group.index.is_some() {
+            writer.comment(format!("Layer `{}`, {}.", group.layer.name, group.layer.mode.name()));
+        }
+        let paths = group.paths(paths);
+        if group.layer.mode == data::LayerMode::Engrave {
+            export_raster(&mut writer, data, &group.layer, &paths, threads)?;
+        } else {
+            export_vector(&mut writer, data, &group.layer, &paths);
+        }
+    }
+
+    return Ok(writer.finish());
+}
+
+
+
+// Cut along every path once per pass. Dashed paths are cut a dash at a time, with the laser off across the gaps.
+fn export_vector(writer : &mut Writer, data : &interpreter::InterpreterData, layer : &data::Layer, paths : &paths::Paths) {
+    writer.comment(format!("Power {}%, speed {} {}/min, {} passes.", exporter::number(layer.power, 1), exporter::number(layer.speed, 1), data.units.name(), layer.passes));
+    for pass in 0..layer.passes {
+        writer.comment(format!("Pass {} of {}.", pass + 1, layer.passes));
+        for path in paths.paths.iter() {
+            writer.comment(paths.equations[path.equation].clone());
+            for dash in style::dashes(&path.points, &data.styles[path.equation].dash) {
+                writer.travel(dash[0]);
+                let mut from = dash[0];
+                for segment in fitting::fit_arcs(&dash, fitting::stage_tolerance(data)) {
+                    match segment {
+                        fitting::Segment::Arc   {to, centre, clockwise} => {
+                            writer.burn_arc(from, to, centre, clockwise, layer.power, layer.speed);
+                            from = to;
+                        },
+                        // Curves are only fitted for SVG and pages.
+                        fitting::Segment::Line  {to}                    |
+                        fitting::Segment::Cubic {to, ..}                => {
+                            writer.burn(to, layer.power, layer.speed);
+                            from = to;
+                        }
+                    }
+                }
+                writer.laser_off();
+            }
+        }
+    }
+}
+
+
+
+// Settings for engraving rows, in units. `left` is the left edge of the image and `pixel` the width of a pixel.
+struct Scan {
+    left     : f64,
+    pixel    : f64,
+    overscan : f64,
+    speed    : f64
+}
+
+
+
+// Engrave the rendered image row by row, once per pass, with each pixel's power set by how dark it is.
+// The line interval is the height of an image row, so `#resolution` sets the engraving resolution.
+// Rows alternate direction and run past their ends by the overscan, so the head is at full speed over the image.
+// With `#skip_blank`, blank rows and the blank ends of rows are skipped.
+fn export_raster(writer : &mut Writer, data : &interpreter::InterpreterData, layer : &data::Layer, paths : &paths::Paths, threads : usize) -> Result<(), String> {
+    let resolution = data.pixel_resolution();
+    let frame      = data.frame();
+    let pixel      = data::Point {
+        x : frame.w / resolution.x as f64,
+        y : frame.h / resolution.y as f64
+    };
+    let scan       = Scan {
+        left     : frame.x,
+        pixel    : pixel.x,
+        overscan : overscan(data),
+        speed    : layer.speed
+    };
+    let full       = writer.spindle_for(layer.power);
+    let width      = resolution.x as usize;
+
+    writer.comment(format!("Raster at {} by {} pixels, power {}%, speed {} {}/min, {} passes.", resolution.x, resolution.y, exporter::number(layer.power, 1), exporter::number(scan.speed, 1), data.units.name(), layer.passes));
+    let mut forward = true;
+    for pass in 0..layer.passes {
+        writer.comment(format!("Pass {} of {}.", pass + 1, layer.passes));
+        let mut row = 0;
+        renderer::render_rows(data, paths, threads, false, |pixels| {
+            for line in pixels.chunks(width * 4) {
+                let y      = frame.y + frame.h - (row as f64 + 0.5) * pixel.y;
+                let powers = line.chunks(4).map(|p| {
+                    let luminance = 0.299 * p[0] as f64 + 0.587 * p[1] as f64 + 0.114 * p[2] as f64;
+                    return ((1.0 - luminance / 255.0) * full as f64).round() as i64;
+                }).collect::<Vec<i64>>();
+                if engrave_row(writer, &powers, y, forward, &scan, data.skip_blank) {
+                    forward = ! forward;
+                }
+                row += 1;
+            }
+            return Ok(());
+        })?;
+    }
+    return Ok(());
+}
+
+
+
+// Engrave a row of pixel powers at height `y`, left to right if `forward`. Returns false if the row was skipped.
+fn engrave_row(writer : &mut Writer, powers : &[i64], y : f64, forward : bool, scan : &Scan, skip_blank : bool) -> bool {
+    let mut start = 0;
+    let mut end   = powers.len();
+    if skip_blank {
+        start = match powers.iter().position(|p| *p > 0) {
+            Some(start) => start,
+            None        => return false
+        };
+        end = powers.iter().rposition(|p| *p > 0).unwrap() + 1;
+    }
+    let edge = |column : usize| scan.left + column as f64 * scan.pixel;
+
+    // Pixels with the same power are burnt in one move, ending at the far edge of the last pixel.
+    if forward {
+        writer.travel(data::Point {x : edge(start) - scan.overscan, y : y});
+        writer.scan(edge(start), 0, scan.speed);
+        for column in start..end {
+            if column + 1 == end || powers[column + 1] != powers[column] {
+                writer.scan(edge(column + 1), powers[column], scan.speed);
+            }
+        }
+        writer.scan(edge(end) + scan.overscan, 0, scan.speed);
+    } else {
+        writer.travel(data::Point {x : edge(end) + scan.overscan, y : y});
+        writer.scan(edge(end), 0, scan.speed);
+        for column in (start..end).rev() {
+            if column == start || powers[column - 1] != powers[column] {
+                writer.scan(edge(column), powers[column], scan.speed);
+            }
+        }
+        writer.scan(edge(start) - scan.overscan, 0, scan.speed);
+    }
+    return true;
+}
+
+
+
+// Get the point on the bed, in units, which becomes the machine's zero.
+// The frame's lower left corner unless set by `#origin`, which is given in frame units and scaled by `#size`.
+pub fn origin(data : &interpreter::InterpreterData) -> data::Point {
+    let frame = data.frame();
+    if data.set_origin {
+        return data::Point {
+            x : data.origin.x * data.scale(),
+            y : data.origin.y * data.scale()
+        };
+    }
+    return data::Point {
+        x : frame.x,
+        y : frame.y
+    };
+}
+
+
+
+// Get the feed rate in units per minute. The default is given in millimetres per minute, so it is converted.
+pub fn feed_rate(data : &interpreter::InterpreterData) -> f64 {
+    if data.set_speed {
+        return data.speed;
+    }
+    return data.speed / data.units.millimetres();
+}
+
+
+
+// Get how far engraved rows run past the image in units. The default is given in millimetres, so it is converted.
+pub fn overscan(data : &interpreter::InterpreterData) -> f64 {
+    if data.set_overscan {
+        return data.overscan;
+    }
+    return data.overscan / data.units.millimetres();
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::run;
+    use super::super::ordering;
+
+    // Trace, order and write the script as G-code on `threads` threads, returning the program.
+    fn program(script : &str, threads : usize) -> String {
+        let (data, mut paths) = run::trace_text(script, threads);
+        if data.ordering != data::Ordering::Script {
+            ordering::optimise(&mut paths, origin(&data), data.ordering);
+        }
+        return document(&data, &paths, threads).unwrap();
+    }
+
+    // The expected program is made with `lrinser testdata/layers.lrn --threads 1 --export testdata/layers.gcode`,
+    // and should only be made again after checking the changes to it are wanted.
+    #[test]
+    fn matches_golden_output() {
+        let script   = include_str!("../../testdata/layers.lrn");
+        let expected = include_str!("../../testdata/layers.gcode");
+        for threads in [1, 4] {
+            assert!(program(script, threads) == expected, "The program written on {} threads differs from testdata/layers.gcode.", threads);
+        }
+    }
+}
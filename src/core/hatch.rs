@@ -0,0 +1,154 @@
+use super::data;
+use super::interpreter;
+use super::paths;
+
+
+
+// Function for filling the closed paths of equations with a `#hatch` with lines, emitted as ordinary open paths.
+// The region of an equation is everything inside an odd number of its closed paths, so a loop inside a loop is a hole.
+// Every line is a whole number of spacings from the origin, so fills of different shapes line up.
+// The closed paths filled are only kept if the hatch has an outline, and come after their lines.
+pub fn fill(data : &interpreter::InterpreterData, paths : &mut paths::Paths) {
+    let mut res = vec![];
+    for (equation, hatch) in data.hatches.iter().enumerate() {
+        let own = paths.paths.iter().filter(|path| path.equation == equation);
+        if hatch.spacing == 0.0 {
+            res.extend(own.cloned());
+            continue;
+        }
+        let loops = own.clone().filter(|path| path.closed).map(|path| &path.points[..]).collect::<Vec<&[data::Point]>>();
+        let mut angles = vec![hatch.angle];
+        if hatch.cross {
+            angles.push(hatch.angle + 90.0);
+        }
+        let mut backwards = false;
+        for angle in angles {
+            for mut row in rows(&loops, hatch.spacing, angle) {
+                if backwards {
+                    row.reverse();
+                    for line in row.iter_mut() {
+                        line.reverse();
+                    }
+                }
+                if hatch.alternate {
+                    backwards = ! backwards;
+                }
+                res.extend(row.into_iter().map(|line| paths::Path {
+                    equation : equation,
+                    closed   : false,
+                    points   : line.to_vec()
+                }));
+            }
+        }
+        res.extend(own.filter(|path| hatch.outline || ! path.closed).cloned());
+    }
+    paths.paths = res;
+}
+
+
+
+// Get the lines inside some loops at `angle` degrees, `spacing` apart, as rows of lines running forwards.
+// Each row is found by measuring the loops across and along the lines, and pairing up where the row crosses their edges.
+fn rows(loops : &[&[data::Point]], spacing : f64, angle : f64) -> Vec<Vec<[data::Point; 2]>> {
+    let (sin, cos) = angle.to_radians().sin_cos();
+    let along      = |point : &data::Point| point.x * cos + point.y * sin;
+    let across     = |point : &data::Point| point.y * cos - point.x * sin;
+    let place      = |along : f64, across : f64| data::Point {
+        x : along * cos - across * sin,
+        y : along * sin + across * cos
+    };
+
+    let mut lo = f64::INFINITY;
+    let mut hi = f64::NEG_INFINITY;
+    for points in loops.iter() {
+        for point in points.iter() {
+            lo = lo.min(across(point));
+            hi = hi.max(across(point));
+        }
+    }
+
+    let mut res = vec![];
+    let mut row = (lo / spacing).ceil();
+    while row * spacing <= hi {
+        let position      = row * spacing;
+        let mut crossings = vec![];
+        for points in loops.iter() {
+            for pair in points.windows(2) {
+                let (a, b) = (across(&pair[0]), across(&pair[1]));
+                // An edge is crossed when its ends are on either side of the row, so a row through a corner crosses it once.
+                if (a > position) != (b > position) {
+                    let (start, end) = (along(&pair[0]), along(&pair[1]));
+                    crossings.push(start + (position - a) / (b - a) * (end - start));
+                }
+            }
+        }
+        crossings.sort_by(|a, b| a.total_cmp(b));
+        let lines = crossings.chunks_exact(2)
+            .filter(|pair| pair[1] > pair[0])
+            .map(|pair| [place(pair[0], position), place(pair[1], position)])
+            .collect::<Vec<[data::Point; 2]>>();
+        if ! lines.is_empty() {
+            res.push(lines);
+        }
+        row += 1.0;
+    }
+    return res;
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::run;
+
+    // Fill a square from (0.5, 0.5) to (10.5, 10.5) with the hatch of a script.
+    fn fill_square(script : &str) -> Vec<paths::Path> {
+        let data      = run::interpret_text(script);
+        let corners   = [(0.5, 0.5), (10.5, 0.5), (10.5, 10.5), (0.5, 10.5), (0.5, 0.5)];
+        let mut paths = paths::Paths {
+            frame     : paths::Frame {x : 0.0, y : 0.0, w : 11.0, h : 11.0},
+            equations : vec!["square".to_string()],
+            paths     : vec![paths::Path {
+                equation : 0,
+                closed   : true,
+                points   : corners.iter().map(|(x, y)| data::Point {x : *x, y : *y}).collect()
+            }]
+        };
+        fill(&data, &mut paths);
+        return paths.paths;
+    }
+
+    #[test]
+    fn lines_fill_a_square() {
+        let paths      = fill_square("#hatch(1.5, 30)\ny = 0\n");
+        let (sin, cos) = 30.0_f64.to_radians().sin_cos();
+        let mut rows   = vec![];
+        for path in paths.iter() {
+            assert!(! path.closed && path.points.len() == 2);
+            let (a, b) = (path.points[0], path.points[1]);
+            // Lines run at the angle of the hatch, and end on the edges of the square.
+            assert!(((b.y - a.y) * cos - (b.x - a.x) * sin).abs() <= 1e-9, "{:?} is not at 30 degrees.", path.points);
+            for point in [a, b] {
+                assert!((0.5 - 1e-9..=10.5 + 1e-9).contains(&point.x) && (0.5 - 1e-9..=10.5 + 1e-9).contains(&point.y), "{:?} is outside the square.", point);
+                assert!([point.x - 0.5, 10.5 - point.x, point.y - 0.5, 10.5 - point.y].iter().any(|d| d.abs() <= 1e-9), "{:?} is not on an edge.", point);
+            }
+            rows.push(a.y * cos - a.x * sin);
+        }
+
+        // Rows are a whole number of spacings from the origin, one spacing apart, covering the square from corner to corner.
+        let lo = (0.5 * cos - 10.5 * sin) / 1.5;
+        let hi = (10.5 * cos - 0.5 * sin) / 1.5;
+        assert_eq!(rows.len(), (hi.floor() - lo.ceil()) as usize + 1);
+        for (index, row) in rows.iter().enumerate() {
+            assert!((row - (lo.ceil() + index as f64) * 1.5).abs() <= 1e-9, "Row {} is at {}.", index, row);
+        }
+    }
+
+    #[test]
+    fn outline_is_kept_after_lines() {
+        let paths = fill_square("#hatch(1.5, 30, \"outline\")\ny = 0\n");
+        assert!(paths.len() > 1);
+        assert!(paths[..(paths.len() - 1)].iter().all(|path| ! path.closed) && paths[paths.len() - 1].closed);
+    }
+}
@@ -0,0 +1,72 @@
+use super::data;
+use super::interpreter;
+use super::paths;
+use super::renderer;
+use super::exporter;
+use super::layers;
+use super::style;
+
+
+
+// Function for writing paths as HPGL for plotters and vinyl cutters.
+// Positions are in plotter units from the frame's lower left corner. Each layer is drawn with its own pen,
+// as is each equation without a layer. Plotters have 8 pens, so they are reused after the 8th.
+// Dashed paths are drawn a dash at a time, lifting the pen across the gaps.
+pub fn export(data : &interpreter::InterpreterData, paths : &paths::Paths) -> renderer::RendererResult {
+    return exporter::write(&data.export, document(data, paths));
+}
+
+
+
+// Build the program written by `export`.
+pub fn document(data : &interpreter::InterpreterData, paths : &paths::Paths) -> String {
+    let scale     = data::HPGL_UNITS_PER_MILLIMETRE * data.units.millimetres();
+    let position  = |point : &data::Point| (
+        ((point.x - paths.frame.x) * scale).round() as i64,
+        ((point.y - paths.frame.y) * scale).round() as i64
+    );
+
+    let mut res = vec!["IN;".to_string()];
+    for (index, group) in layers::settings(data).iter().enumerate() {
+        res.push(format!("SP{};", index % data::HPGL_PENS + 1));
+        for path in paths.paths.iter().filter(|path| group.equations.contains(&path.equation)) {
+            for dash in style::dashes(&path.points, &data.styles[path.equation].dash) {
+                let mut points = dash.iter().map(position).collect::<Vec<(i64, i64)>>();
+                // Points closer than a plotter unit land on the same position.
+                points.dedup();
+                if points.len() < 2 {
+                    continue;
+                }
+                res.push(format!("PU{},{};", points[0].0, points[0].1));
+                let moves = points[1..].iter().map(|(x, y)| format!("{},{}", x, y)).collect::<Vec<String>>();
+                res.push(format!("PD{};", moves.join(",")));
+            }
+        }
+        res.push("PU;".to_string());
+    }
+    res.push("SP0;".to_string());
+
+    return res.join("\n") + "\n";
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::run;
+
+    // There are 40 plotter units to the millimetre, and each equation without a layer gets its own pen.
+    #[test]
+    fn pens_and_moves() {
+        let (data, paths) = run::trace_text("#tolerance(0.1)\n#frame(0, 0, 10, 10)\ny = x\ny = 5 [dash(2, 3)]\n", 1);
+        assert_eq!(document(&data, &paths), "IN;\nSP1;\nPU0,0;\nPD400,400;\nPU;\nSP2;\nPU0,200;\nPD80,200;\nPU200,200;\nPD280,200;\nPU;\nSP0;\n");
+    }
+
+    #[test]
+    fn inch_units() {
+        let (data, paths) = run::trace_text("#units(\"in\")\n#tolerance(0.01)\n#frame(0, 0, 2, 2)\ny = x\n", 1);
+        assert_eq!(document(&data, &paths), "IN;\nSP1;\nPU0,0;\nPD2032,2032;\nPU;\nSP0;\n");
+    }
+}
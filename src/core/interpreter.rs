@@ -2,26 +2,119 @@ use super::data;
 use super::defaults;
 use super::nodes;
 use super::exceptions;
+use super::machine;
+use super::paths;
 
 
 
 // Data class for the renderer.
 #[derive(Clone, Debug)]
 pub struct InterpreterData {
-    pub set_frame      : bool,
-    pub set_resolution : bool,
-    pub set_export     : bool,
-    pub print_now      : bool,
+    pub set_frame            : bool,
+    pub set_resolution       : bool,
+    pub set_export           : bool,
+    pub print_now            : bool,
+    pub set_division_by_zero : bool,
+    pub set_thickness        : bool,
+    pub antialias            : bool,
+    pub set_units            : bool,
+    pub set_power            : bool,
+    pub set_speed            : bool,
+    pub set_passes           : bool,
+    pub set_origin           : bool,
+    pub raster               : bool,
+    pub set_overscan         : bool,
+    pub skip_blank           : bool,
+    pub axes                 : bool,
+    pub set_tolerance        : bool,
+    pub set_ordering         : bool,
+    pub set_machine          : bool,
+    pub set_size             : bool,
+    pub set_dpi              : bool,
 
-    pub position       : data::Vector2,
-    pub size           : data::Vector2,
+    pub position             : data::Vector2,
+    pub size                 : data::Vector2,
 
-    pub resolution     : data::Vector2,
+    pub resolution           : data::Vector2,
 
-    pub export         : String,
+    // Size of the frame on the bed in units, with zero on an axis to keep the frame's aspect ratio.
+    pub physical_size        : data::Point,
+    // Pixels per inch for the axes of the resolution left at zero.
+    pub dpi                  : f64,
 
+    pub export               : String,
 
-    pub equations      : Vec<nodes::Node>
+    pub division_by_zero     : data::ExceptionLevel,
+
+    pub thickness            : f64,
+
+    pub units                : data::Unit,
+
+    pub power                : f64,
+    pub speed                : f64,
+    pub passes               : i32,
+    pub origin               : data::Point,
+
+    pub overscan             : f64,
+
+    pub tolerance            : f64,
+
+    pub ordering             : data::Ordering,
+
+    // Kerf for the equations without a layer.
+    pub kerf                 : data::Kerf,
+
+    // Hatch for the equations that follow, and the hatch of each equation.
+    pub hatch                : data::Hatch,
+    pub hatches              : Vec<data::Hatch>,
+
+    // Layers in the order they are first given, the layer for the equations that follow, and the layer of each equation.
+    // Equations before the first `#layer` have no layer.
+    pub layers               : Vec<data::Layer>,
+    pub layer                : Option<usize>,
+    pub equation_layers      : Vec<Option<usize>>,
+
+    // Style of each equation, from the attributes after it.
+    pub styles               : Vec<data::Style>,
+
+    // Machine the job is checked against and written for, if a profile was given.
+    pub machine              : Option<data::Machine>,
+
+    pub equations            : Vec<nodes::Node>
+}
+impl InterpreterData {
+    // Get how many units on the bed a frame unit is, from `#size`. One frame unit is one unit unless the size is set.
+    pub fn scale(&self) -> f64 {
+        if ! self.set_size {
+            return 1.0;
+        }
+        if self.physical_size.x > 0.0 {
+            return self.physical_size.x / self.size.x as f64;
+        }
+        return self.physical_size.y / self.size.y as f64;
+    }
+
+    // Get the frame on the bed in units, which is the `#frame` scaled about zero.
+    pub fn frame(&self) -> paths::Frame {
+        let scale = self.scale();
+        return paths::Frame {
+            x : self.position.x as f64 * scale,
+            y : self.position.y as f64 * scale,
+            w : self.size.x as f64 * scale,
+            h : self.size.y as f64 * scale
+        };
+    }
+
+    // Get the image resolution. Axes left at zero by `#resolution` are worked out from the frame on the bed at `#dpi`.
+    pub fn pixel_resolution(&self) -> data::Vector2 {
+        let frame  = self.frame();
+        let pixels = self.dpi / data::Unit::Inch.millimetres() * self.units.millimetres();
+        let axis   = |length : f64| ((length * pixels).round() as i32).max(1);
+        return data::Vector2 {
+            x : if self.resolution.x == 0 { axis(frame.w) } else { self.resolution.x },
+            y : if self.resolution.y == 0 { axis(frame.h) } else { self.resolution.y }
+        };
+    }
 }
 
 
@@ -37,31 +130,113 @@ pub struct InterpreterResult {
 
 
 // Function for interpreting a vector of nodes.
-pub fn interpret(nodes : Vec<nodes::Node>) -> InterpreterResult {
+// A `machine` given here replaces the script's `#machine`, and the job is checked against the machine once everything is read.
+pub fn interpret(nodes : Vec<nodes::Node>, machine : Option<data::Machine>) -> InterpreterResult {
     let mut data = InterpreterData {
-        set_frame      : false,
-        set_resolution : false,
-        set_export     : false,
-        print_now      : false,
+        set_frame            : false,
+        set_resolution       : false,
+        set_export           : false,
+        print_now            : false,
+        set_division_by_zero : false,
+        set_thickness        : false,
+        antialias            : false,
+        set_units            : false,
+        set_power            : false,
+        set_speed            : false,
+        set_passes           : false,
+        set_origin           : false,
+        raster               : false,
+        set_overscan         : false,
+        skip_blank           : false,
+        axes                 : false,
+        set_tolerance        : false,
+        set_ordering         : false,
+        set_machine          : false,
+        set_size             : false,
+        set_dpi              : false,
+
+        position             : defaults::POSITION,
+        size                 : defaults::SIZE,
+
+        resolution           : defaults::RESOLUTION,
+
+        physical_size        : data::Point {x : 0.0, y : 0.0},
+        dpi                  : defaults::DPI,
+
+        export               : defaults::EXPORT.to_string(),
+
+        division_by_zero     : defaults::DIVISION_BY_ZERO,
+
+        thickness            : defaults::THICKNESS,
+
+        units                : defaults::UNITS,
+
+        power                : defaults::POWER,
+        speed                : defaults::SPEED,
+        passes               : defaults::PASSES,
+        origin               : data::Point {x : 0.0, y : 0.0},
+
+        overscan             : defaults::OVERSCAN,
+
+        tolerance            : defaults::TOLERANCE,
 
-        position       : defaults::POSITION,
-        size           : defaults::SIZE,
+        ordering             : defaults::ORDERING,
 
-        resolution     : defaults::RESOLUTION,
+        kerf                 : defaults::KERF,
 
-        export         : defaults::EXPORT.to_string(),
+        hatch                : defaults::HATCH,
+        hatches              : vec![],
 
+        layers               : vec![],
+        layer                : None,
+        equation_layers      : vec![],
 
-        equations      : vec![]
+        styles               : vec![],
+
+        machine              : None,
+
+        equations            : vec![]
     };
 
-    let mut exceptions = vec![];
+    let mut exceptions    = vec![];
+    let mut machine_range = data::Range {
+        filename : "".to_string(),
+        start    : 0,
+        end      : 0
+    };
+    let mut size_range    = machine_range.clone();
     for node in nodes {
+        match node.base {
+            nodes::NodeBase::HeaderFuncMachine {..} => machine_range = node.range.clone(),
+            nodes::NodeBase::HeaderFuncSize    {..} => size_range    = node.range.clone(),
+            _                                       => ()
+        }
         let mut result = match node.base {
             nodes::NodeBase::HeaderFuncFrame      {x, y, w, h}  => interpret_headerfunc_frame(data.clone(), node.range, x, y, w, h),
             nodes::NodeBase::HeaderFuncResolution {w, h}        => interpret_headerfunc_resolution(data.clone(), node.range, w, h),
             nodes::NodeBase::HeaderFuncExport     {filename}    => interpret_headerfunc_export(data.clone(), node.range, filename),
             nodes::NodeBase::HeaderFuncPrintNow                 => interpret_headerfunc_print_now(data.clone(), node.range),
+            nodes::NodeBase::HeaderFuncDivisionByZero {level}   => interpret_headerfunc_division_by_zero(data.clone(), node.range, level),
+            nodes::NodeBase::HeaderFuncThickness  {thickness}   => interpret_headerfunc_thickness(data.clone(), node.range, thickness),
+            nodes::NodeBase::HeaderFuncAntialias                => interpret_headerfunc_antialias(data.clone(), node.range),
+            nodes::NodeBase::HeaderFuncUnits      {units}       => interpret_headerfunc_units(data.clone(), node.range, units),
+            nodes::NodeBase::HeaderFuncPower      {power}       => interpret_headerfunc_power(data.clone(), node.range, power),
+            nodes::NodeBase::HeaderFuncSpeed      {speed}       => interpret_headerfunc_speed(data.clone(), node.range, speed),
+            nodes::NodeBase::HeaderFuncPasses     {passes}      => interpret_headerfunc_passes(data.clone(), node.range, passes),
+            nodes::NodeBase::HeaderFuncOrigin     {x, y}        => interpret_headerfunc_origin(data.clone(), node.range, x, y),
+            nodes::NodeBase::HeaderFuncRaster                   => interpret_headerfunc_raster(data.clone(), node.range),
+            nodes::NodeBase::HeaderFuncOverscan   {overscan}    => interpret_headerfunc_overscan(data.clone(), node.range, overscan),
+            nodes::NodeBase::HeaderFuncSkipBlank                => interpret_headerfunc_skip_blank(data.clone(), node.range),
+            nodes::NodeBase::HeaderFuncAxes                     => interpret_headerfunc_axes(data.clone(), node.range),
+            nodes::NodeBase::HeaderFuncTolerance  {tolerance}   => interpret_headerfunc_tolerance(data.clone(), node.range, tolerance),
+            nodes::NodeBase::HeaderFuncOptimiseTravel {mode}    => interpret_headerfunc_optimise_travel(data.clone(), node.range, mode),
+            nodes::NodeBase::HeaderFuncKerf       {width, join} => interpret_headerfunc_kerf(data.clone(), node.range, width, join),
+            nodes::NodeBase::HeaderFuncHatch {spacing, angle, options} => interpret_headerfunc_hatch(data.clone(), node.range, spacing, angle, options),
+            nodes::NodeBase::HeaderFuncLayer {name, mode, power, speed, passes} => interpret_headerfunc_layer(data.clone(), node.range, name, mode, power, speed, passes),
+            nodes::NodeBase::HeaderFuncMachine {name}           => interpret_headerfunc_machine(data.clone(), node.range, name, machine.is_some()),
+            nodes::NodeBase::HeaderFuncSize       {w, h}        => interpret_headerfunc_size(data.clone(), node.range, w, h),
+            nodes::NodeBase::HeaderFuncDpi        {dpi}         => interpret_headerfunc_dpi(data.clone(), node.range, dpi),
+            nodes::NodeBase::Style {colour, width, dash}        => interpret_style(data.clone(), node.range, colour, width, dash),
             nodes::NodeBase::EqualsExpression     {left, right} => interpret_equation_equals(data.clone(), node.range, *left, *right),
             _                                                   => {
                 println!("{}", node);
@@ -74,8 +249,36 @@ pub fn interpret(nodes : Vec<nodes::Node>) -> InterpreterResult {
             exceptions.append(&mut result.exceptions);
         }
     }
+
+    // The frame may be given after `#size`, so the aspect ratio is checked once both are known.
+    if data.set_size && data.physical_size.x > 0.0 && data.physical_size.y > 0.0 {
+        let frame  = data.size.x as f64 / data.size.y as f64;
+        let aspect = data.physical_size.x / data.physical_size.y;
+        if (aspect - frame).abs() > frame * 1e-9 {
+            exceptions.push(exceptions::InterpreterException {
+                base    : exceptions::InterpreterExceptionBase::InvalidValueException,
+                message : "Size must have the aspect ratio of the frame. Give a width or height of 0 to keep it.".to_string(),
+                range   : size_range
+            });
+        }
+    }
+
+    // Check the job against the machine, before anything is traced or written.
+    if machine.is_some() {
+        data.machine = machine;
+    }
+    // The check runs even when the script has other errors, so every problem is reported at once.
+    if let Some(machine) = &data.machine {
+        for message in machine::check(&data, machine) {
+            exceptions.push(exceptions::InterpreterException {
+                base    : exceptions::InterpreterExceptionBase::InvalidValueException,
+                message : message,
+                range   : machine_range.clone()
+            });
+        }
+    }
     return InterpreterResult {
-        success    : exceptions.len() <= 0,
+        success    : exceptions.len() == 0,
         data       : data,
         exceptions : exceptions
     };
@@ -151,6 +354,17 @@ pub fn interpret_headerfunc_resolution(mut data : InterpreterData, range : data:
             }]
         };
     }
+    if data.set_dpi && w > 0 && h > 0 {
+        return InterpreterResult {
+            success    : false,
+            data       : data,
+            exceptions : vec![exceptions::InterpreterException {
+                base    : exceptions::InterpreterExceptionBase::InvalidValueException,
+                message : "Resolution can not give both width and height when `dpi` is set.".to_string(),
+                range   : range
+            }]
+        };
+    }
 
     data.set_resolution = true;
     data.resolution = data::Vector2 {
@@ -218,17 +432,36 @@ pub fn interpret_headerfunc_print_now(mut data : InterpreterData, range : data::
 
 
 
-// Equality equation interpreter
-pub fn interpret_equation_equals(mut data : InterpreterData, range : data::Range, left : nodes::Node, right : nodes::Node) -> InterpreterResult {
-    data.equations.push(
-        nodes::Node {
-            base  : nodes::NodeBase::EqualsExpression {
-                left  : Box::new(left),
-                right : Box::new(right)
-            },
-            range : range
+// Division by zero header function interpreter
+pub fn interpret_headerfunc_division_by_zero(mut data : InterpreterData, range : data::Range, level : String) -> InterpreterResult {
+    let level = match level.as_str() {
+        "warning" => data::ExceptionLevel::Warning,
+        "error"   => data::ExceptionLevel::Error,
+        _         => return InterpreterResult {
+            success    : false,
+            data       : data,
+            exceptions : vec![exceptions::InterpreterException {
+                base    : exceptions::InterpreterExceptionBase::InvalidValueException,
+                message : format!("Division by zero level must be `warning` or `error`, not `{}`.", data::escapify(level)),
+                range   : range
+            }]
         }
-    );
+    };
+    if data.set_division_by_zero {
+        return InterpreterResult {
+            success    : false,
+            data       : data,
+            exceptions : vec![exceptions::InterpreterException {
+                base    : exceptions::InterpreterExceptionBase::HeaderAlreadyAccessedException,
+                message : "Header `division_by_zero` has already been accessed.".to_string(),
+                range   : range
+            }]
+        };
+    }
+
+    data.set_division_by_zero = true;
+    data.division_by_zero     = level;
+
     return InterpreterResult {
         success    : true,
         data       : data,
@@ -238,12 +471,879 @@ pub fn interpret_equation_equals(mut data : InterpreterData, range : data::Range
 
 
 
-// Fallback for unknown nodes
-pub fn interpret_unknown(data : InterpreterData) -> InterpreterResult {
-    println!("Unknown node found");
+// Thickness header function interpreter
+pub fn interpret_headerfunc_thickness(mut data : InterpreterData, range : data::Range, thickness : f64) -> InterpreterResult {
+    if thickness < 0.0 {
+        return InterpreterResult {
+            success    : false,
+            data       : data,
+            exceptions : vec![exceptions::InterpreterException {
+                base    : exceptions::InterpreterExceptionBase::InvalidValueException,
+                message : "Thickness must be at least 0.".to_string(),
+                range   : range
+            }]
+        };
+    }
+    if data.set_thickness {
+        return InterpreterResult {
+            success    : false,
+            data       : data,
+            exceptions : vec![exceptions::InterpreterException {
+                base    : exceptions::InterpreterExceptionBase::HeaderAlreadyAccessedException,
+                message : "Header `thickness` has already been accessed.".to_string(),
+                range   : range
+            }]
+        };
+    }
+
+    data.set_thickness = true;
+    data.thickness     = thickness;
+
+    return InterpreterResult {
+        success    : true,
+        data       : data,
+        exceptions : vec![]
+    };
+}
+
+
+
+// Antialias header function interpreter
+pub fn interpret_headerfunc_antialias(mut data : InterpreterData, range : data::Range) -> InterpreterResult {
+    if data.antialias {
+        return InterpreterResult {
+            success    : false,
+            data       : data,
+            exceptions : vec![exceptions::InterpreterException {
+                base    : exceptions::InterpreterExceptionBase::HeaderAlreadyAccessedException,
+                message : "Header `antialias` has already been accessed.".to_string(),
+                range   : range
+            }]
+        };
+    }
+
+    data.antialias = true;
+
+    return InterpreterResult {
+        success    : true,
+        data       : data,
+        exceptions : vec![]
+    };
+}
+
+
+
+// Units header function interpreter
+pub fn interpret_headerfunc_units(mut data : InterpreterData, range : data::Range, units : String) -> InterpreterResult {
+    let units = match units.as_str() {
+        "mm" => data::Unit::Millimetre,
+        "in" => data::Unit::Inch,
+        _    => return InterpreterResult {
+            success    : false,
+            data       : data,
+            exceptions : vec![exceptions::InterpreterException {
+                base    : exceptions::InterpreterExceptionBase::InvalidValueException,
+                message : format!("Units must be `mm` or `in`, not `{}`.", data::escapify(units)),
+                range   : range
+            }]
+        }
+    };
+    if data.set_units {
+        return InterpreterResult {
+            success    : false,
+            data       : data,
+            exceptions : vec![exceptions::InterpreterException {
+                base    : exceptions::InterpreterExceptionBase::HeaderAlreadyAccessedException,
+                message : "Header `units` has already been accessed.".to_string(),
+                range   : range
+            }]
+        };
+    }
+
+    data.set_units = true;
+    data.units     = units;
+
+    return InterpreterResult {
+        success    : true,
+        data       : data,
+        exceptions : vec![]
+    };
+}
+
+
+
+// Power header function interpreter
+pub fn interpret_headerfunc_power(mut data : InterpreterData, range : data::Range, power : f64) -> InterpreterResult {
+    if ! (0.0..=100.0).contains(&power) {
+        return InterpreterResult {
+            success    : false,
+            data       : data,
+            exceptions : vec![exceptions::InterpreterException {
+                base    : exceptions::InterpreterExceptionBase::InvalidValueException,
+                message : "Power must be between 0 and 100.".to_string(),
+                range   : range
+            }]
+        };
+    }
+    if data.set_power {
+        return InterpreterResult {
+            success    : false,
+            data       : data,
+            exceptions : vec![exceptions::InterpreterException {
+                base    : exceptions::InterpreterExceptionBase::HeaderAlreadyAccessedException,
+                message : "Header `power` has already been accessed.".to_string(),
+                range   : range
+            }]
+        };
+    }
+
+    data.set_power = true;
+    data.power     = power;
+
+    return InterpreterResult {
+        success    : true,
+        data       : data,
+        exceptions : vec![]
+    };
+}
+
+
+
+// Speed header function interpreter
+pub fn interpret_headerfunc_speed(mut data : InterpreterData, range : data::Range, speed : f64) -> InterpreterResult {
+    if speed <= 0.0 {
+        return InterpreterResult {
+            success    : false,
+            data       : data,
+            exceptions : vec![exceptions::InterpreterException {
+                base    : exceptions::InterpreterExceptionBase::InvalidValueException,
+                message : "Speed must be more than 0.".to_string(),
+                range   : range
+            }]
+        };
+    }
+    if data.set_speed {
+        return InterpreterResult {
+            success    : false,
+            data       : data,
+            exceptions : vec![exceptions::InterpreterException {
+                base    : exceptions::InterpreterExceptionBase::HeaderAlreadyAccessedException,
+                message : "Header `speed` has already been accessed.".to_string(),
+                range   : range
+            }]
+        };
+    }
+
+    data.set_speed = true;
+    data.speed     = speed;
+
+    return InterpreterResult {
+        success    : true,
+        data       : data,
+        exceptions : vec![]
+    };
+}
+
+
+
+// Passes header function interpreter
+pub fn interpret_headerfunc_passes(mut data : InterpreterData, range : data::Range, passes : i32) -> InterpreterResult {
+    if passes < 1 {
+        return InterpreterResult {
+            success    : false,
+            data       : data,
+            exceptions : vec![exceptions::InterpreterException {
+                base    : exceptions::InterpreterExceptionBase::InvalidValueException,
+                message : "Passes must be at least 1.".to_string(),
+                range   : range
+            }]
+        };
+    }
+    if data.set_passes {
+        return InterpreterResult {
+            success    : false,
+            data       : data,
+            exceptions : vec![exceptions::InterpreterException {
+                base    : exceptions::InterpreterExceptionBase::HeaderAlreadyAccessedException,
+                message : "Header `passes` has already been accessed.".to_string(),
+                range   : range
+            }]
+        };
+    }
+
+    data.set_passes = true;
+    data.passes     = passes;
+
     return InterpreterResult {
         success    : true,
         data       : data,
         exceptions : vec![]
+    };
+}
+
+
+
+// Origin header function interpreter
+pub fn interpret_headerfunc_origin(mut data : InterpreterData, range : data::Range, x : f64, y : f64) -> InterpreterResult {
+    if data.set_origin {
+        return InterpreterResult {
+            success    : false,
+            data       : data,
+            exceptions : vec![exceptions::InterpreterException {
+                base    : exceptions::InterpreterExceptionBase::HeaderAlreadyAccessedException,
+                message : "Header `origin` has already been accessed.".to_string(),
+                range   : range
+            }]
+        };
+    }
+
+    data.set_origin = true;
+    data.origin     = data::Point {x : x, y : y};
+
+    return InterpreterResult {
+        success    : true,
+        data       : data,
+        exceptions : vec![]
+    };
+}
+
+
+
+// Raster header function interpreter
+pub fn interpret_headerfunc_raster(mut data : InterpreterData, range : data::Range) -> InterpreterResult {
+    if data.raster {
+        return InterpreterResult {
+            success    : false,
+            data       : data,
+            exceptions : vec![exceptions::InterpreterException {
+                base    : exceptions::InterpreterExceptionBase::HeaderAlreadyAccessedException,
+                message : "Header `raster` has already been accessed.".to_string(),
+                range   : range
+            }]
+        };
+    }
+
+    data.raster = true;
+
+    return InterpreterResult {
+        success    : true,
+        data       : data,
+        exceptions : vec![]
+    };
+}
+
+
+
+// Overscan header function interpreter
+pub fn interpret_headerfunc_overscan(mut data : InterpreterData, range : data::Range, overscan : f64) -> InterpreterResult {
+    if overscan < 0.0 {
+        return InterpreterResult {
+            success    : false,
+            data       : data,
+            exceptions : vec![exceptions::InterpreterException {
+                base    : exceptions::InterpreterExceptionBase::InvalidValueException,
+                message : "Overscan must be at least 0.".to_string(),
+                range   : range
+            }]
+        };
+    }
+    if data.set_overscan {
+        return InterpreterResult {
+            success    : false,
+            data       : data,
+            exceptions : vec![exceptions::InterpreterException {
+                base    : exceptions::InterpreterExceptionBase::HeaderAlreadyAccessedException,
+                message : "Header `overscan` has already been accessed.".to_string(),
+                range   : range
+            }]
+        };
+    }
+
+    data.set_overscan = true;
+    data.overscan     = overscan;
+
+    return InterpreterResult {
+        success    : true,
+        data       : data,
+        exceptions : vec![]
+    };
+}
+
+
+
+// Skip blank header function interpreter
+pub fn interpret_headerfunc_skip_blank(mut data : InterpreterData, range : data::Range) -> InterpreterResult {
+    if data.skip_blank {
+        return InterpreterResult {
+            success    : false,
+            data       : data,
+            exceptions : vec![exceptions::InterpreterException {
+                base    : exceptions::InterpreterExceptionBase::HeaderAlreadyAccessedException,
+                message : "Header `skip_blank` has already been accessed.".to_string(),
+                range   : range
+            }]
+        };
+    }
+
+    data.skip_blank = true;
+
+    return InterpreterResult {
+        success    : true,
+        data       : data,
+        exceptions : vec![]
+    };
+}
+
+
+
+// Axes header function interpreter
+pub fn interpret_headerfunc_axes(mut data : InterpreterData, range : data::Range) -> InterpreterResult {
+    if data.axes {
+        return InterpreterResult {
+            success    : false,
+            data       : data,
+            exceptions : vec![exceptions::InterpreterException {
+                base    : exceptions::InterpreterExceptionBase::HeaderAlreadyAccessedException,
+                message : "Header `axes` has already been accessed.".to_string(),
+                range   : range
+            }]
+        };
+    }
+
+    data.axes = true;
+
+    return InterpreterResult {
+        success    : true,
+        data       : data,
+        exceptions : vec![]
+    };
+}
+
+
+
+// Tolerance header function interpreter
+pub fn interpret_headerfunc_tolerance(mut data : InterpreterData, range : data::Range, tolerance : f64) -> InterpreterResult {
+    if tolerance < 0.0 {
+        return InterpreterResult {
+            success    : false,
+            data       : data,
+            exceptions : vec![exceptions::InterpreterException {
+                base    : exceptions::InterpreterExceptionBase::InvalidValueException,
+                message : "Tolerance must be at least 0.".to_string(),
+                range   : range
+            }]
+        };
+    }
+    if data.set_tolerance {
+        return InterpreterResult {
+            success    : false,
+            data       : data,
+            exceptions : vec![exceptions::InterpreterException {
+                base    : exceptions::InterpreterExceptionBase::HeaderAlreadyAccessedException,
+                message : "Header `tolerance` has already been accessed.".to_string(),
+                range   : range
+            }]
+        };
+    }
+
+    data.set_tolerance = true;
+    data.tolerance     = tolerance;
+
+    return InterpreterResult {
+        success    : true,
+        data       : data,
+        exceptions : vec![]
+    };
+}
+
+
+
+// Optimise travel header function interpreter
+pub fn interpret_headerfunc_optimise_travel(mut data : InterpreterData, range : data::Range, mode : String) -> InterpreterResult {
+    let ordering = match mode.as_str() {
+        "order"   => data::Ordering::Order,
+        "reverse" => data::Ordering::Reverse,
+        _         => return InterpreterResult {
+            success    : false,
+            data       : data,
+            exceptions : vec![exceptions::InterpreterException {
+                base    : exceptions::InterpreterExceptionBase::InvalidValueException,
+                message : format!("Travel optimisation must be `order` or `reverse`, not `{}`.", data::escapify(mode)),
+                range   : range
+            }]
+        }
+    };
+    if data.set_ordering {
+        return InterpreterResult {
+            success    : false,
+            data       : data,
+            exceptions : vec![exceptions::InterpreterException {
+                base    : exceptions::InterpreterExceptionBase::HeaderAlreadyAccessedException,
+                message : "Header `optimise_travel` has already been accessed.".to_string(),
+                range   : range
+            }]
+        };
+    }
+
+    data.set_ordering = true;
+    data.ordering     = ordering;
+
+    return InterpreterResult {
+        success    : true,
+        data       : data,
+        exceptions : vec![]
+    };
+}
+
+
+
+// Kerf header function interpreter
+// The kerf applies to the whole of the current layer, or to the equations without a layer before any `#layer`,
+// so it can not be changed once the layer has equations.
+pub fn interpret_headerfunc_kerf(mut data : InterpreterData, range : data::Range, width : f64, join : String) -> InterpreterResult {
+    if width < 0.0 {
+        return InterpreterResult {
+            success    : false,
+            data       : data,
+            exceptions : vec![exceptions::InterpreterException {
+                base    : exceptions::InterpreterExceptionBase::InvalidValueException,
+                message : "Kerf must be at least 0.".to_string(),
+                range   : range
+            }]
+        };
+    }
+    let join = match join.as_str() {
+        "miter" => data::Join::Miter,
+        "round" => data::Join::Round,
+        _       => return InterpreterResult {
+            success    : false,
+            data       : data,
+            exceptions : vec![exceptions::InterpreterException {
+                base    : exceptions::InterpreterExceptionBase::InvalidValueException,
+                message : format!("Kerf join must be `miter` or `round`, not `{}`.", data::escapify(join)),
+                range   : range
+            }]
+        }
+    };
+
+    let kerf   = data::Kerf {
+        width : width,
+        join  : join
+    };
+    let (current, message) = match data.layer {
+        Some(index) => (data.layers[index].kerf, format!("The kerf of layer `{}` can not be changed after its equations.", data::escapify(data.layers[index].name.clone()))),
+        None        => (data.kerf, "The kerf of the equations without a layer can not be changed after them.".to_string())
+    };
+    if kerf != current && data.equation_layers.contains(&data.layer) {
+        return InterpreterResult {
+            success    : false,
+            data       : data,
+            exceptions : vec![exceptions::InterpreterException {
+                base    : exceptions::InterpreterExceptionBase::InvalidValueException,
+                message : message,
+                range   : range
+            }]
+        };
+    }
+    match data.layer {
+        Some(index) => data.layers[index].kerf = kerf,
+        None        => data.kerf = kerf
+    }
+
+    return InterpreterResult {
+        success    : true,
+        data       : data,
+        exceptions : vec![]
+    };
+}
+
+
+
+// Hatch header function interpreter
+// The hatch applies to the equations after it, so it can be set again between them.
+pub fn interpret_headerfunc_hatch(mut data : InterpreterData, range : data::Range, spacing : f64, angle : f64, options : Vec<String>) -> InterpreterResult {
+    if spacing < 0.0 {
+        return InterpreterResult {
+            success    : false,
+            data       : data,
+            exceptions : vec![exceptions::InterpreterException {
+                base    : exceptions::InterpreterExceptionBase::InvalidValueException,
+                message : "Hatch spacing must be at least 0.".to_string(),
+                range   : range
+            }]
+        };
+    }
+    let mut hatch = data::Hatch {
+        spacing   : spacing,
+        angle     : angle,
+        cross     : false,
+        alternate : false,
+        outline   : false
+    };
+    for option in options {
+        match option.as_str() {
+            "cross"     => hatch.cross     = true,
+            "alternate" => hatch.alternate = true,
+            "outline"   => hatch.outline   = true,
+            _           => return InterpreterResult {
+                success    : false,
+                data       : data,
+                exceptions : vec![exceptions::InterpreterException {
+                    base    : exceptions::InterpreterExceptionBase::InvalidValueException,
+                    message : format!("Hatch option must be `cross`, `alternate` or `outline`, not `{}`.", data::escapify(option)),
+                    range   : range
+                }]
+            }
+        }
+    }
+
+    data.hatch = hatch;
+
+    return InterpreterResult {
+        success    : true,
+        data       : data,
+        exceptions : vec![]
+    };
+}
+
+
+
+// Layer header function interpreter
+// The layer applies to the equations after it. Giving a layer's name again goes back to it, if the settings match.
+pub fn interpret_headerfunc_layer(mut data : InterpreterData, range : data::Range, name : String, mode : String, power : f64, speed : f64, passes : i32) -> InterpreterResult {
+    let mut exceptions = vec![];
+    let mode = match mode.as_str() {
+        "cut"     => Some(data::LayerMode::Cut),
+        "score"   => Some(data::LayerMode::Score),
+        "engrave" => Some(data::LayerMode::Engrave),
+        _         => {
+            exceptions.push(format!("Layer mode must be `cut`, `score` or `engrave`, not `{}`.", data::escapify(mode)));
+            None
+        }
+    };
+    if name.is_empty() {
+        exceptions.push("Layer name must not be empty.".to_string());
+    }
+    if ! (0.0..=100.0).contains(&power) {
+        exceptions.push("Layer power must be between 0 and 100.".to_string());
+    }
+    if speed <= 0.0 {
+        exceptions.push("Layer speed must be greater than 0.".to_string());
+    }
+    if passes < 1 {
+        exceptions.push("Layer passes must be at least 1.".to_string());
+    }
+    let mut layer = data::Layer {
+        name   : name,
+        mode   : mode.unwrap_or(data::LayerMode::Cut),
+        power  : power,
+        speed  : speed,
+        passes : passes,
+        kerf   : defaults::KERF
+    };
+    let existing = data.layers.iter().position(|other| other.name == layer.name);
+    if let Some(index) = existing {
+        // The kerf is not given with the layer, so going back to it keeps the kerf it has.
+        layer.kerf = data.layers[index].kerf;
+        if exceptions.is_empty() && data.layers[index] != layer {
+            exceptions.push(format!("Layer `{}` has already been given different settings.", data::escapify(layer.name.clone())));
+        }
+    }
+    if ! exceptions.is_empty() {
+        return InterpreterResult {
+            success    : false,
+            data       : data,
+            exceptions : exceptions.into_iter().map(|message| exceptions::InterpreterException {
+                base    : exceptions::InterpreterExceptionBase::InvalidValueException,
+                message : message,
+                range   : range.clone()
+            }).collect()
+        };
+    }
+
+    data.layer = match existing {
+        Some(index) => Some(index),
+        None        => {
+            data.layers.push(layer);
+            Some(data.layers.len() - 1)
+        }
+    };
+
+    return InterpreterResult {
+        success    : true,
+        data       : data,
+        exceptions : vec![]
+    };
+}
+
+
+
+// Equality equation interpreter
+pub fn interpret_equation_equals(mut data : InterpreterData, range : data::Range, left : nodes::Node, right : nodes::Node) -> InterpreterResult {
+    data.hatches.push(data.hatch);
+    data.equation_layers.push(data.layer);
+    data.styles.push(defaults::STYLE);
+    data.equations.push(
+        nodes::Node {
+            base  : nodes::NodeBase::EqualsExpression {
+                left  : Box::new(left),
+                right : Box::new(right)
+            },
+            range : range
+        }
+    );
+    return InterpreterResult {
+        success    : true,
+        data       : data,
+        exceptions : vec![]
+    };
+}
+
+
+
+// Machine header function interpreter
+// The profile is read from beside the script, found from the header's filename.
+pub fn interpret_headerfunc_machine(mut data : InterpreterData, range : data::Range, name : String, overridden : bool) -> InterpreterResult {
+    if data.set_machine {
+        return InterpreterResult {
+            success    : false,
+            data       : data,
+            exceptions : vec![exceptions::InterpreterException {
+                base    : exceptions::InterpreterExceptionBase::HeaderAlreadyAccessedException,
+                message : "Header `machine` has already been accessed.".to_string(),
+                range   : range
+            }]
+        };
+    }
+    // A machine given on the command line takes the place of the script's, so its profile is not read.
+    if overridden {
+        data.set_machine = true;
+        return InterpreterResult {
+            success    : true,
+            data       : data,
+            exceptions : vec![]
+        };
+    }
+    let machine = match machine::load(&name, &range.filename) {
+        Ok(machine) => machine,
+        Err(e)      => return InterpreterResult {
+            success    : false,
+            data       : data,
+            exceptions : vec![exceptions::InterpreterException {
+                base    : exceptions::InterpreterExceptionBase::InvalidValueException,
+                message : e,
+                range   : range
+            }]
+        }
+    };
+
+    data.set_machine = true;
+    data.machine     = Some(machine);
+
+    return InterpreterResult {
+        success    : true,
+        data       : data,
+        exceptions : vec![]
+    };
+}
+
+
+
+// Style attribute interpreter
+// The parser only allows attributes straight after an equation, so they style the last one.
+pub fn interpret_style(mut data : InterpreterData, range : data::Range, colour : Option<String>, width : Option<f64>, dash : Option<Vec<f64>>) -> InterpreterResult {
+    let mut exceptions = vec![];
+    let mut style      = defaults::STYLE;
+    if let Some(colour) = colour {
+        style.colour = data::Colour::parse(&colour);
+        if style.colour.is_none() {
+            exceptions.push(format!("Colour must be `#rgb`, `#rrggbb` or a colour name, not `{}`.", data::escapify(colour)));
+        }
+    }
+    if let Some(width) = width {
+        if width < 0.0 {
+            exceptions.push("Width must be at least 0.".to_string());
+        }
+        style.width = Some(width);
+    }
+    if let Some(dash) = dash {
+        if dash.iter().any(|length| *length <= 0.0) {
+            exceptions.push("Dash lengths must be greater than 0.".to_string());
+        }
+        style.dash = dash;
+    }
+    if ! exceptions.is_empty() {
+        return InterpreterResult {
+            success    : false,
+            data       : data,
+            exceptions : exceptions.into_iter().map(|message| exceptions::InterpreterException {
+                base    : exceptions::InterpreterExceptionBase::InvalidValueException,
+                message : message,
+                range   : range.clone()
+            }).collect()
+        };
+    }
+
+    if let Some(last) = data.styles.last_mut() {
+        *last = style;
+    }
+
+    return InterpreterResult {
+        success    : true,
+        data       : data,
+        exceptions : vec![]
+    };
+}
+
+
+
+// Size header function interpreter
+pub fn interpret_headerfunc_size(mut data : InterpreterData, range : data::Range, w : f64, h : f64) -> InterpreterResult {
+    if w < 0.0 || h < 0.0 || (w == 0.0 && h == 0.0) {
+        return InterpreterResult {
+            success    : false,
+            data       : data,
+            exceptions : vec![exceptions::InterpreterException {
+                base    : exceptions::InterpreterExceptionBase::InvalidValueException,
+                message : "Size width and height must be at least 0, and not both 0.".to_string(),
+                range   : range
+            }]
+        };
+    }
+    if data.set_size {
+        return InterpreterResult {
+            success    : false,
+            data       : data,
+            exceptions : vec![exceptions::InterpreterException {
+                base    : exceptions::InterpreterExceptionBase::HeaderAlreadyAccessedException,
+                message : "Header `size` has already been accessed.".to_string(),
+                range   : range
+            }]
+        };
+    }
+
+    data.set_size      = true;
+    data.physical_size = data::Point {
+        x : w,
+        y : h
+    };
+
+    return InterpreterResult {
+        success    : true,
+        data       : data,
+        exceptions : vec![]
+    };
+}
+
+
+
+// Dpi header function interpreter
+pub fn interpret_headerfunc_dpi(mut data : InterpreterData, range : data::Range, dpi : f64) -> InterpreterResult {
+    if dpi <= 0.0 {
+        return InterpreterResult {
+            success    : false,
+            data       : data,
+            exceptions : vec![exceptions::InterpreterException {
+                base    : exceptions::InterpreterExceptionBase::InvalidValueException,
+                message : "Dpi must be more than 0.".to_string(),
+                range   : range
+            }]
+        };
+    }
+    if data.set_dpi {
+        return InterpreterResult {
+            success    : false,
+            data       : data,
+            exceptions : vec![exceptions::InterpreterException {
+                base    : exceptions::InterpreterExceptionBase::HeaderAlreadyAccessedException,
+                message : "Header `dpi` has already been accessed.".to_string(),
+                range   : range
+            }]
+        };
+    }
+    if data.resolution.x > 0 && data.resolution.y > 0 {
+        return InterpreterResult {
+            success    : false,
+            data       : data,
+            exceptions : vec![exceptions::InterpreterException {
+                base    : exceptions::InterpreterExceptionBase::InvalidValueException,
+                message : "Dpi can not be set when `resolution` gives both width and height.".to_string(),
+                range   : range
+            }]
+        };
+    }
+
+    data.set_dpi = true;
+    data.dpi     = dpi;
+
+    return InterpreterResult {
+        success    : true,
+        data       : data,
+        exceptions : vec![]
+    };
+}
+
+
+
+// Fallback for unknown nodes
+pub fn interpret_unknown(data : InterpreterData) -> InterpreterResult {
+    println!("Unknown node found");
+    return InterpreterResult {
+        success    : true,
+        data       : data,
+        exceptions : vec![]
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::lexer;
+    use super::super::parser;
+
+    // Interpret a script held in memory, with a machine given as if on the command line.
+    fn interpret_with(script : &str, machine : Option<data::Machine>) -> InterpreterResult {
+        let lexer_res  = lexer::lex("test.lrn".to_string(), script.to_string());
+        let parser_res = parser::parse(lexer_res.tokens);
+        assert!(parser_res.success, "{}", parser_res.exception);
+        return interpret(parser_res.nodes, machine);
+    }
+
+    // Machine with a 100 by 100 mm bed.
+    fn small_machine() -> data::Machine {
+        return data::Machine {
+            name        : "small".to_string(),
+            bed_width   : 100.0,
+            bed_height  : 100.0,
+            max_spindle : 1000.0,
+            max_feed    : 6000.0,
+            max_power   : None
+        };
+    }
+
+    #[test]
+    fn command_line_machine_replaces_script() {
+        let res = interpret_with("#machine(\"missing\")\n#frame(0, 0, 50, 50)\ny = x\n", None);
+        assert!(! res.success);
+        assert!(res.exceptions[0].message.contains("was not found"), "{}", res.exceptions[0]);
+
+        let res = interpret_with("#machine(\"missing\")\n#frame(0, 0, 50, 50)\ny = x\n", Some(small_machine()));
+        assert!(res.success, "{}", res.exceptions.iter().map(|e| e.to_string()).collect::<Vec<String>>().join("\n"));
+        assert_eq!(res.data.machine.unwrap().name, "small");
+
+        // The header may still only be given once.
+        let res = interpret_with("#machine(\"missing\")\n#machine(\"missing\")\ny = x\n", Some(small_machine()));
+        assert!(matches!(res.exceptions[0].base, exceptions::InterpreterExceptionBase::HeaderAlreadyAccessedException));
+    }
+
+    #[test]
+    fn machine_checked_alongside_other_errors() {
+        let res      = interpret_with("#frame(0, 0, 500, 50)\n#tolerance(1)\n#tolerance(2)\ny = x\n", Some(small_machine()));
+        let messages = res.exceptions.iter().map(|e| e.message.clone()).collect::<Vec<String>>();
+        assert!(! res.success);
+        assert!(messages.iter().any(|message| message.contains("already been accessed")), "{:?}", messages);
+        assert!(messages.iter().any(|message| message.contains("does not fit")), "{:?}", messages);
     }
 }
@@ -0,0 +1,95 @@
+use super::data;
+use super::interpreter;
+use super::paths;
+use super::gcode;
+
+
+
+// Equations burnt with the same laser settings, in script order.
+// `index` is the index of the `#layer`, or none for the equations before any `#layer`, which use the script's settings.
+#[derive(Clone, Debug)]
+pub struct Group {
+    pub index     : Option<usize>,
+    pub layer     : data::Layer,
+    pub equations : Vec<usize>
+}
+impl Group {
+    // Get the paths traced from the group's equations.
+    pub fn paths(&self, paths : &paths::Paths) -> paths::Paths {
+        return paths::Paths {
+            frame     : paths.frame,
+            equations : paths.equations.clone(),
+            paths     : paths.paths.iter().filter(|path| self.equations.contains(&path.equation)).cloned().collect()
+        };
+    }
+}
+
+
+
+// Function for grouping equations by layer, starting with the equations without one. Groups with no equations are left out.
+pub fn groups(data : &interpreter::InterpreterData) -> Vec<Group> {
+    let mut res = vec![Group {
+        index     : None,
+        layer     : default_layer(data),
+        equations : vec![]
+    }];
+    for (index, layer) in data.layers.iter().enumerate() {
+        res.push(Group {
+            index     : Some(index),
+            layer     : layer.clone(),
+            equations : vec![]
+        });
+    }
+    for (equation, layer) in data.equation_layers.iter().enumerate() {
+        res[layer.map_or(0, |index| index + 1)].equations.push(equation);
+    }
+    res.retain(|group| ! group.equations.is_empty());
+    return res;
+}
+
+
+
+// Function for grouping equations for exports with a setting per group, like plotter pens.
+// Each equation without a layer is put in a group of its own, so they can still be told apart.
+pub fn settings(data : &interpreter::InterpreterData) -> Vec<Group> {
+    let mut res = vec![];
+    for group in groups(data) {
+        match group.index {
+            Some(_) => res.push(group),
+            None    => for equation in group.equations.iter() {
+                res.push(Group {
+                    index     : None,
+                    layer     : group.layer.clone(),
+                    equations : vec![*equation]
+                });
+            }
+        }
+    }
+    return res;
+}
+
+
+
+// Get the layer of the equations without one, from `#power`, `#speed` and `#passes`.
+// They are engraved if `#raster` is set, and cut otherwise.
+fn default_layer(data : &interpreter::InterpreterData) -> data::Layer {
+    return data::Layer {
+        name   : "default".to_string(),
+        mode   : if data.raster { data::LayerMode::Engrave } else { data::LayerMode::Cut },
+        power  : data.power,
+        speed  : gcode::feed_rate(data),
+        passes : data.passes,
+        kerf   : data.kerf
+    };
+}
+
+
+
+// Get the colour an equation is drawn in, and its AutoCAD colour index. Equations without a layer are black.
+pub fn colour(data : &interpreter::InterpreterData, equation : usize) -> (data::Colour, i32) {
+    return match data.equation_layers[equation] {
+        Some(index) => data::LAYER_COLOURS[index % data::LAYER_COLOURS.len()],
+        None        => (data::Colour {r : 0.0, g : 0.0, b : 0.0, a : 1.0}, 7)
+    };
+}
+
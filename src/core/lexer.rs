@@ -82,7 +82,7 @@ impl Lexer {
             }
 
 
-            // Variables, Header functions, and Functions.
+            // Variables, Header functions, Functions, and Attributes.
             else if data::ALPHABETIC.contains(self.ch) {
                 let mut identifier = "".to_string();
                 let     start      = self.pos;
@@ -102,6 +102,12 @@ impl Lexer {
                         value : identifier.to_string(),
                         range : self.get_range(start)
                     })
+                } else if data::ATTRIBUTES.contains(&identifier.as_str()) {
+                    tokens.push(tokens::Token {
+                        name  : tokens::TK_ATTRIBUTE.to_string(),
+                        value : identifier.to_string(),
+                        range : self.get_range(start)
+                    })
                 } else {
                     for (i, ch) in identifier.chars().enumerate() {
                         tokens.push(tokens::Token {
@@ -298,6 +304,26 @@ impl Lexer {
             }
 
 
+            else if self.ch == '[' {
+                tokens.push(tokens::Token {
+                    name  : tokens::TK_LBRACKET.to_string(),
+                    value : "".to_string(),
+                    range : self.get_range(self.pos)
+                });
+                self.advance();
+            }
+
+
+            else if self.ch == ']' {
+                tokens.push(tokens::Token {
+                    name  : tokens::TK_RBRACKET.to_string(),
+                    value : "".to_string(),
+                    range : self.get_range(self.pos)
+                });
+                self.advance();
+            }
+
+
             // Header function hash.
             else if self.ch == '#' {
                 tokens.push(tokens::Token {
@@ -0,0 +1,123 @@
+use super::data;
+use super::interpreter;
+use super::paths;
+use super::renderer;
+use super::exporter;
+use super::gcode;
+use super::layers;
+use super::style;
+
+
+
+// Function for writing paths as a LightBurn project.
+// Each `#layer` gets a cut setting with its laser settings, as does each equation without a layer,
+// and every path becomes a shape on its equation's setting. Dashed paths become a shape for each dash.
+// LightBurn works in millimetres from the lower left of the bed, so positions are measured from the origin.
+pub fn export(data : &interpreter::InterpreterData, paths : &paths::Paths) -> renderer::RendererResult {
+    return match document(data, paths) {
+        Ok(document) => exporter::write(&data.export, document),
+        Err(e)       => exporter::failure(e)
+    };
+}
+
+
+
+// Build the project written by `export`.
+pub fn document(data : &interpreter::InterpreterData, paths : &paths::Paths) -> Result<String, String> {
+    let origin = gcode::origin(data);
+    let scale  = data.units.millimetres();
+    let place  = |point : &data::Point| format!(
+        "V{} {}",
+        exporter::number((point.x - origin.x) * scale, 4),
+        exporter::number((point.y - origin.y) * scale, 4)
+    );
+
+    let mut res = vec![
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>".to_string(),
+        "<LightBurnProject AppVersion=\"1.4.00\" FormatVersion=\"1\" MaterialHeight=\"0\" MirrorX=\"False\" MirrorY=\"False\">".to_string()
+    ];
+    // LightBurn has 30 layers, and sharing them would burn some equations with another's settings.
+    // Equations without a layer are named after their source.
+    let settings = layers::settings(data);
+    if settings.len() > data::LIGHTBURN_LAYERS {
+        return Err(format!("LightBurn projects have {} layers, but the script needs {}, one for each `#layer` and each equation without a layer.", data::LIGHTBURN_LAYERS, settings.len()));
+    }
+    let mut indices = vec![0; paths.equations.len()];
+    for (index, group) in settings.iter().enumerate() {
+        let name = if group.index.is_some() { &group.layer.name } else { &paths.equations[group.equations[0]] };
+        res.extend(cut_setting(data, index, name, &group.layer));
+        for equation in group.equations.iter() {
+            indices[*equation] = index;
+        }
+    }
+    for path in paths.paths.iter() {
+        let dash = &data.styles[path.equation].dash;
+        // Dashes are open, even when the path they come from is closed.
+        let closed = path.closed && dash.is_empty();
+        for points in style::dashes(&path.points, dash) {
+            // A closed path ends where it starts, which the closing line already draws.
+            let count  = if closed { points.len() - 1 } else { points.len() };
+            let verts  = points[..count].iter().map(place).collect::<Vec<String>>();
+            let mut prims = (1..count).map(|i| format!("L{} {}", i - 1, i)).collect::<Vec<String>>();
+            if closed {
+                prims.push(format!("L{} 0", count - 1));
+            }
+            res.push(format!("    <Shape Type=\"Path\" CutIndex=\"{}\">", indices[path.equation]));
+            res.push("        <XForm>1 0 0 1 0 0</XForm>".to_string());
+            res.push(format!("        <VertList>{}</VertList>", verts.join("")));
+            res.push(format!("        <PrimList>{}</PrimList>", prims.join("")));
+            res.push("    </Shape>".to_string());
+        }
+    }
+    res.push("</LightBurnProject>".to_string());
+
+    return Ok(res.join("\n") + "\n");
+}
+
+
+
+// Build a cut setting for a layer. Engraved layers are filled, others are cut along the lines.
+fn cut_setting(data : &interpreter::InterpreterData, index : usize, name : &str, layer : &data::Layer) -> Vec<String> {
+    let engrave = layer.mode == data::LayerMode::Engrave;
+    // LightBurn speeds are in millimetres per second.
+    let speed   = layer.speed * data.units.millimetres() / 60.0;
+    let mut res = vec![
+        format!("    <CutSetting type=\"{}\">", if engrave { "Scan" } else { "Cut" }),
+        format!("        <index Value=\"{}\"/>", index),
+        format!("        <name Value=\"{}\"/>", exporter::escape_xml(name)),
+        format!("        <maxPower Value=\"{}\"/>", exporter::number(layer.power, 2)),
+        format!("        <maxPower2 Value=\"{}\"/>", exporter::number(layer.power, 2)),
+        format!("        <speed Value=\"{}\"/>", exporter::number(speed, 3)),
+        format!("        <numPasses Value=\"{}\"/>", layer.passes),
+        format!("        <priority Value=\"{}\"/>", index)
+    ];
+    if engrave {
+        let interval = data.frame().h / data.pixel_resolution().y as f64 * data.units.millimetres();
+        res.push(format!("        <interval Value=\"{}\"/>", exporter::number(interval, 4)));
+    }
+    res.push("    </CutSetting>".to_string());
+    return res;
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::run;
+
+    // Write a script with `count` equations, each without a layer.
+    fn project(count : usize) -> Result<String, String> {
+        let script        = (0..count).map(|index| format!("y = {}\n", index)).collect::<String>();
+        let (data, paths) = run::trace_text(&format!("#frame(-10, 0, 20, 40)\n{}", script), 1);
+        return document(&data, &paths);
+    }
+
+    #[test]
+    fn too_many_layers_fail() {
+        let res = project(data::LIGHTBURN_LAYERS).unwrap();
+        assert_eq!(res.matches("<CutSetting ").count(), data::LIGHTBURN_LAYERS);
+        let e   = project(data::LIGHTBURN_LAYERS + 1).unwrap_err();
+        assert!(e.starts_with("LightBurn projects have 30 layers, but the script needs 31"), "{}", e);
+    }
+}
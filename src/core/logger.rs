@@ -4,7 +4,7 @@ use colored::Colorize;
 
 
 #[static_init::dynamic]
-static mut start : DateTime<Local> = Local::now();
+static mut START : DateTime<Local> = Local::now();
 
 
 
@@ -75,7 +75,7 @@ pub fn debug<S: AsRef<str>>(text : S) {
 
 
 fn duration() -> String {
-    let duration = (Local::now() - *start.read()).to_std().unwrap();
+    let duration = (Local::now() - *START.read()).to_std().unwrap();
     let hours    = duration.as_secs() / 3600;
     let minutes  = duration.as_secs() % 3600 / 60;
     let seconds  = duration.as_secs() % 60;
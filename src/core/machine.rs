@@ -0,0 +1,103 @@
+use std::fs;
+use std::path::Path;
+
+use toml;
+use super::data;
+use super::interpreter;
+use super::layers;
+use super::gcode;
+use super::exporter;
+
+
+
+// Function for reading the profile of the machine called `name`, from `machines/<name>.toml`.
+// The directory beside the script is looked in first, then the working directory.
+pub fn load(name : &str, script : &str) -> Result<data::Machine, String> {
+    // Names are kept to a plain file name, so profiles can not be read from elsewhere.
+    if name.is_empty() || ! name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err(format!("Machine name must only hold letters, digits, `-` and `_`, not `{}`.", data::escapify(name.to_string())));
+    }
+    let file   = format!("{}.toml", name);
+    let beside = Path::new(script).parent().unwrap_or(Path::new("")).join(data::MACHINE_DIRECTORY).join(&file);
+    let paths  = [beside, Path::new(data::MACHINE_DIRECTORY).join(&file)];
+    let path   = match paths.iter().find(|path| path.is_file()) {
+        Some(path) => path,
+        None       => {
+            let mut places = paths.iter().map(|path| format!("`{}`", data::escapify(path.parent().unwrap().display().to_string()))).collect::<Vec<String>>();
+            places.dedup();
+            return Err(format!("Machine profile `{}` was not found in {}.", file, places.join(" or ")));
+        }
+    };
+
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e)   => return Err(format!("Could not read machine profile `{}`: {}", data::escapify(path.display().to_string()), e))
+    };
+    let mut res : data::Machine = match toml::from_str(&text) {
+        Ok(machine) => machine,
+        Err(e)      => return Err(format!("Could not read machine profile `{}`: {}", data::escapify(path.display().to_string()), e.message()))
+    };
+    res.name = name.to_string();
+
+    if res.bed_width <= 0.0 || res.bed_height <= 0.0 {
+        return Err(format!("Bed of machine `{}` must be more than 0 wide and high.", res.name));
+    }
+    if res.max_spindle < 1.0 {
+        return Err(format!("Maximum spindle value of machine `{}` must be at least 1.", res.name));
+    }
+    if res.max_feed <= 0.0 {
+        return Err(format!("Maximum feed of machine `{}` must be more than 0.", res.name));
+    }
+    if let Some(power) = res.max_power {
+        if ! (0.0..=100.0).contains(&power) {
+            return Err(format!("Maximum power of machine `{}` must be between 0 and 100.", res.name));
+        }
+    }
+    return Ok(res);
+}
+
+
+
+// Function for checking a job can be run on a machine, returning what is wrong with it.
+// The frame, and the overscan if anything is engraved, must be on the bed, measured from the origin.
+// Every layer's speed must be within the machine's maximum feed, and its power within the maximum power.
+pub fn check(data : &interpreter::InterpreterData, machine : &data::Machine) -> Vec<String> {
+    let mut res  = vec![];
+    let groups   = layers::groups(data);
+    let scale    = data.units.millimetres();
+    let origin   = gcode::origin(data);
+    let overscan = if groups.iter().any(|group| group.layer.mode == data::LayerMode::Engrave) { gcode::overscan(data) } else { 0.0 };
+    let frame    = data.frame();
+    let lo       = data::Point {
+        x : (frame.x - overscan - origin.x) * scale,
+        y : (frame.y - origin.y) * scale
+    };
+    let hi       = data::Point {
+        x : (frame.x + frame.w + overscan - origin.x) * scale,
+        y : (frame.y + frame.h - origin.y) * scale
+    };
+    if lo.x < 0.0 || lo.y < 0.0 || hi.x > machine.bed_width || hi.y > machine.bed_height {
+        res.push(format!(
+            "Job from {}, {} to {}, {} mm does not fit on the {} by {} mm bed of machine `{}`.",
+            exporter::number(lo.x, 3), exporter::number(lo.y, 3), exporter::number(hi.x, 3), exporter::number(hi.y, 3),
+            exporter::number(machine.bed_width, 3), exporter::number(machine.bed_height, 3), machine.name
+        ));
+    }
+
+    for group in groups.iter() {
+        let on    = match group.index {
+            Some(_) => format!(" on layer `{}`", data::escapify(group.layer.name.clone())),
+            None    => "".to_string()
+        };
+        let speed = group.layer.speed * scale;
+        if speed > machine.max_feed {
+            res.push(format!("Speed of {} mm/min{} is above the maximum feed of {} mm/min of machine `{}`.", exporter::number(speed, 3), on, exporter::number(machine.max_feed, 3), machine.name));
+        }
+        if let Some(power) = machine.max_power {
+            if group.layer.power > power {
+                res.push(format!("Power of {}%{} is above the maximum power of {}% of machine `{}`.", exporter::number(group.layer.power, 3), on, exporter::number(power, 3), machine.name));
+            }
+        }
+    }
+    return res;
+}
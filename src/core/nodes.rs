@@ -1,5 +1,7 @@
 use std::fmt;
 use std::ops;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
 
 use super::logger;
 use super::data;
@@ -42,7 +44,7 @@ pub enum NodeBase {
 
     // Integer, Float, etc
     Number {
-        value : f32
+        value : f64
     },
     // Single character
     Variable {
@@ -82,6 +84,74 @@ pub enum NodeBase {
         filename : String
     },
     HeaderFuncPrintNow,
+    HeaderFuncDivisionByZero {
+        level : String
+    },
+    HeaderFuncThickness {
+        thickness : f64
+    },
+    HeaderFuncAntialias,
+    HeaderFuncUnits {
+        units : String
+    },
+    HeaderFuncPower {
+        power : f64
+    },
+    HeaderFuncSpeed {
+        speed : f64
+    },
+    HeaderFuncPasses {
+        passes : i32
+    },
+    HeaderFuncOrigin {
+        x : f64,
+        y : f64
+    },
+    HeaderFuncRaster,
+    HeaderFuncOverscan {
+        overscan : f64
+    },
+    HeaderFuncSkipBlank,
+    HeaderFuncAxes,
+    HeaderFuncTolerance {
+        tolerance : f64
+    },
+    HeaderFuncOptimiseTravel {
+        mode : String
+    },
+    HeaderFuncKerf {
+        width : f64,
+        join  : String
+    },
+    HeaderFuncHatch {
+        spacing : f64,
+        angle   : f64,
+        options : Vec<String>
+    },
+    HeaderFuncLayer {
+        name   : String,
+        mode   : String,
+        power  : f64,
+        speed  : f64,
+        passes : i32
+    },
+    HeaderFuncMachine {
+        name : String
+    },
+    HeaderFuncSize {
+        w : f64,
+        h : f64
+    },
+    HeaderFuncDpi {
+        dpi : f64
+    },
+
+    // [attribute_name(arg1, arg2, etc), etc]
+    Style {
+        colour : Option<String>,
+        width  : Option<f64>,
+        dash   : Option<Vec<f64>>
+    },
 
     // function_name(arg1, arg2, etc)
     FunctionSin {
@@ -108,16 +178,21 @@ pub enum NodeBase {
 }
 // Method for evaluating the value of an expression.
 impl Node {
-    pub fn simplify(&self, x : f32) -> EvaluationResult {
+    pub fn simplify(&self, x : f64) -> EvaluationResult {
+        return self.simplify_at(x, None);
+    }
+
+    // Evaluate with `y` substituted when a value is given, used by the numeric solver.
+    pub fn simplify_at(&self, x : f64, y : Option<f64>) -> EvaluationResult {
         return match &self.base {
 
             // Handle left = right.
             NodeBase::EqualsExpression  {left, right} => {
-                let left_res  = left.simplify(x);
+                let left_res  = left.simplify_at(x, y);
                 if ! left_res.success {
                     return left_res;
                 }
-                let right_res = right.simplify(x);
+                let right_res = right.simplify_at(x, y);
                 if ! right_res.success {
                     return right_res;
                 }
@@ -174,7 +249,7 @@ impl Node {
 
             // If variable name is `x`, return the value of x.
             NodeBase::Variable          {name}        => {
-                if *name == "x".to_string() {
+                if *name == "x" {
                     return EvaluationResult {
                         success   : true,
                         value     : Node {
@@ -189,10 +264,19 @@ impl Node {
                             range   : self.range.clone()
                         }
                     };
-                } else if *name == "y".to_string() {
+                } else if *name == "y" {
+                    let value = match y {
+                        Some(y) => Node {
+                            base  : NodeBase::MultipleNumber {
+                                value : data::MultipleValues::new_single(y)
+                            },
+                            range : self.range.clone()
+                        },
+                        None    => self.clone()
+                    };
                     return EvaluationResult {
                         success   : true,
-                        value     : self.clone(),
+                        value     : value,
                         exception : exceptions::RendererException {
                             base    : exceptions::RendererExceptionBase::NoException,
                             message : "".to_string(),
@@ -233,11 +317,11 @@ impl Node {
 
             // Evaluate left and right values, then add right to left.
             NodeBase::AdditionOperation {left, right} => {
-                let left_res  = left.simplify(x);
+                let left_res  = left.simplify_at(x, y);
                 if ! left_res.success {
                     return left_res;
                 }
-                let right_res = right.simplify(x);
+                let right_res = right.simplify_at(x, y);
                 if ! right_res.success {
                     return right_res;
                 }
@@ -288,11 +372,11 @@ impl Node {
 
             // Evaluate left and right values, then subtraft right from left.
             NodeBase::SubtractionOperation {left, right} => {
-                let left_res  = left.simplify(x);
+                let left_res  = left.simplify_at(x, y);
                 if ! left_res.success {
                     return left_res;
                 }
-                let right_res = right.simplify(x);
+                let right_res = right.simplify_at(x, y);
                 if ! right_res.success {
                     return right_res;
                 }
@@ -343,11 +427,11 @@ impl Node {
 
             // Evaluate left and right values, then multiply left and right.
             NodeBase::MultiplicationOperation {left, right} => {
-                let left_res  = left.simplify(x);
+                let left_res  = left.simplify_at(x, y);
                 if ! left_res.success {
                     return left_res;
                 }
-                let right_res = right.simplify(x);
+                let right_res = right.simplify_at(x, y);
                 if ! right_res.success {
                     return right_res;
                 }
@@ -399,11 +483,11 @@ impl Node {
             // If right is not 0, evaluate left and right values, then divide right from left.
             NodeBase::DivisionOperation {left, right} => {
                 
-                let left_res  = left.simplify(x);
+                let left_res  = left.simplify_at(x, y);
                 if ! left_res.success {
                     return left_res;
                 }
-                let right_res = right.simplify(x);
+                let right_res = right.simplify_at(x, y);
                 if ! right_res.success {
                     return right_res;
                 }
@@ -454,7 +538,7 @@ impl Node {
 
             // Evaluate argument and return sin value.
             NodeBase::FunctionSin {a} => {
-                let res = a.simplify(x);
+                let res = a.simplify_at(x, y);
                 if ! res.success {
                     return res;
                 }
@@ -481,7 +565,7 @@ impl Node {
 
             // Evaluate argument and return cos value.
             NodeBase::FunctionCos {a} => {
-                let res = a.simplify(x);
+                let res = a.simplify_at(x, y);
                 if ! res.success {
                     return res;
                 }
@@ -502,7 +586,7 @@ impl Node {
 
             // Evaluate argument and return tan value.
             NodeBase::FunctionTan {a} => {
-                let res = a.simplify(x);
+                let res = a.simplify_at(x, y);
                 if ! res.success {
                     return res;
                 }
@@ -523,17 +607,17 @@ impl Node {
 
             // Evaluate arguments and return nth root value.
             NodeBase::FunctionRoot {exp, base, user_typed} => {
-                let exp_res = exp.simplify(x);
+                let exp_res = exp.simplify_at(x, y);
                 if ! exp_res.success {
                     return exp_res;
                 }
-                let base_res = base.simplify(x);
+                let base_res = base.simplify_at(x, y);
                 if ! base_res.success {
                     return base_res;
                 }
                 return EvaluationResult {
                     success   : true,
-                    value     : base_res.value.root(exp_res.value, *user_typed),
+                    value     : base_res.value.root(exp_res.value, *user_typed, self.key()),
                     exception : exceptions::RendererException {
                         base    : exceptions::RendererExceptionBase::NoException,
                         message : "".to_string(),
@@ -548,11 +632,11 @@ impl Node {
 
             // Evaluate arguments and return powed value.
             NodeBase::FunctionPow {base, exp} => {
-                let base_res = base.simplify(x);
+                let base_res = base.simplify_at(x, y);
                 if ! base_res.success {
                     return base_res;
                 }
-                let exp_res = exp.simplify(x);
+                let exp_res = exp.simplify_at(x, y);
                 if ! exp_res.success {
                     return exp_res;
                 }
@@ -597,6 +681,38 @@ impl Node {
 
 
 
+    // Check if a variable appears anywhere in the expression.
+    pub fn contains_variable(&self, variable : &str) -> bool {
+        return match &self.base {
+            NodeBase::Variable                {name}            => name == variable,
+            NodeBase::EqualsExpression        {left, right}     |
+            NodeBase::AdditionOperation       {left, right}     |
+            NodeBase::SubtractionOperation    {left, right}     |
+            NodeBase::MultiplicationOperation {left, right}     |
+            NodeBase::DivisionOperation       {left, right}     => left.contains_variable(variable) || right.contains_variable(variable),
+            NodeBase::FunctionSin             {a}               |
+            NodeBase::FunctionCos             {a}               |
+            NodeBase::FunctionTan             {a}               => a.contains_variable(variable),
+            NodeBase::FunctionRoot            {exp, base, ..}   => exp.contains_variable(variable) || base.contains_variable(variable),
+            NodeBase::FunctionPow             {base, exp}       => base.contains_variable(variable) || exp.contains_variable(variable),
+            _                                                   => false
+        };
+    }
+
+    // Check if an equation is in the form `y = f(x)` or `f(x) = y`, which can be evaluated directly.
+    pub fn is_explicit(&self) -> bool {
+        return match &self.base {
+            NodeBase::EqualsExpression {left, right} => {
+                let left_y  = matches!(&left.base, NodeBase::Variable {name} if name == "y");
+                let right_y = matches!(&right.base, NodeBase::Variable {name} if name == "y");
+                (left_y && ! right.contains_variable("y")) || (right_y && ! left.contains_variable("y"))
+            },
+            _ => ! self.contains_variable("y")
+        };
+    }
+
+
+
     fn sin(&self) -> Node {
         match self.base.clone() {
             NodeBase::MultipleNumber {value} => {
@@ -642,16 +758,27 @@ impl Node {
         return self.clone();
     }
 
-    fn root(&self, exp : Node, user_typed : bool) -> Node {
-        panic!("Root");
+    fn root(&self, exp : Node, user_typed : bool, key : u64) -> Node {
+        match (self.base.clone(), exp.base) {
+            (NodeBase::MultipleNumber {value}, NodeBase::MultipleNumber {value : exp}) => {
+                return Node {
+                    base : NodeBase::MultipleNumber {
+                        value : value.root(exp, user_typed, key)
+                    },
+                    range : self.range.clone()
+                }
+            }
+            _ => ()
+        }
+        return self.clone();
     }
 
     fn pow(&self, exp : Node) -> Node {
-        match self.base.clone() {
-            NodeBase::MultipleNumber {ref value} => {
+        match (self.base.clone(), exp.base) {
+            (NodeBase::MultipleNumber {value}, NodeBase::MultipleNumber {value : exp}) => {
                 return Node {
                     base : NodeBase::MultipleNumber {
-                        value : value.clone().pow(value.clone())
+                        value : value.pow(exp)
                     },
                     range : self.range.clone()
                 }
@@ -660,6 +787,13 @@ impl Node {
         }
         return self.clone();
     }
+
+    // Key identifying an expression by its contents, so identical expressions share branch choices.
+    pub fn key(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        format!("{}", self).hash(&mut hasher);
+        return hasher.finish();
+    }
 }
 // Displays for different types of nodes for debugging.
 impl fmt::Display for Node {
@@ -679,6 +813,45 @@ impl fmt::Display for Node {
             NodeBase::HeaderFuncResolution    {w, h}                  => write!(f, "#resolution({}, {})", w, h),
             NodeBase::HeaderFuncExport        {filename}              => write!(f, "#export(`{}`)", data::escapify(filename.clone())),
             NodeBase::HeaderFuncPrintNow                              => write!(f, "#print_now()"),
+            NodeBase::HeaderFuncDivisionByZero {level}                => write!(f, "#division_by_zero(`{}`)", data::escapify(level.clone())),
+            NodeBase::HeaderFuncThickness     {thickness}             => write!(f, "#thickness({})", thickness),
+            NodeBase::HeaderFuncAntialias                             => write!(f, "#antialias()"),
+            NodeBase::HeaderFuncUnits         {units}                 => write!(f, "#units(`{}`)", data::escapify(units.clone())),
+            NodeBase::HeaderFuncPower         {power}                 => write!(f, "#power({})", power),
+            NodeBase::HeaderFuncSpeed         {speed}                 => write!(f, "#speed({})", speed),
+            NodeBase::HeaderFuncPasses        {passes}                => write!(f, "#passes({})", passes),
+            NodeBase::HeaderFuncOrigin        {x, y}                  => write!(f, "#origin({}, {})", x, y),
+            NodeBase::HeaderFuncRaster                                => write!(f, "#raster()"),
+            NodeBase::HeaderFuncOverscan      {overscan}              => write!(f, "#overscan({})", overscan),
+            NodeBase::HeaderFuncSkipBlank                             => write!(f, "#skip_blank()"),
+            NodeBase::HeaderFuncAxes                                  => write!(f, "#axes()"),
+            NodeBase::HeaderFuncTolerance     {tolerance}             => write!(f, "#tolerance({})", tolerance),
+            NodeBase::HeaderFuncOptimiseTravel {mode}                 => write!(f, "#optimise_travel(`{}`)", data::escapify(mode.clone())),
+            NodeBase::HeaderFuncKerf          {width, join}           => write!(f, "#kerf({}, `{}`)", width, data::escapify(join.clone())),
+            NodeBase::HeaderFuncHatch         {spacing, angle, options} => {
+                write!(f, "#hatch({}, {}", spacing, angle)?;
+                for option in options.iter() {
+                    write!(f, ", `{}`", data::escapify(option.clone()))?;
+                }
+                write!(f, ")")
+            },
+            NodeBase::HeaderFuncLayer         {name, mode, power, speed, passes} => write!(f, "#layer(`{}`, `{}`, {}, {}, {})", data::escapify(name.clone()), data::escapify(mode.clone()), power, speed, passes),
+            NodeBase::HeaderFuncMachine       {name}                  => write!(f, "#machine(`{}`)", data::escapify(name.clone())),
+            NodeBase::HeaderFuncSize          {w, h}                  => write!(f, "#size({}, {})", w, h),
+            NodeBase::HeaderFuncDpi           {dpi}                   => write!(f, "#dpi({})", dpi),
+            NodeBase::Style                   {colour, width, dash}   => {
+                let mut attributes = vec![];
+                if let Some(colour) = colour {
+                    attributes.push(format!("colour(`{}`)", data::escapify(colour.clone())));
+                }
+                if let Some(width) = width {
+                    attributes.push(format!("width({})", width));
+                }
+                if let Some(dash) = dash {
+                    attributes.push(format!("dash({})", dash.iter().map(|length| length.to_string()).collect::<Vec<String>>().join(", ")));
+                }
+                write!(f, "[{}]", attributes.join(", "))
+            },
             NodeBase::FunctionSin             {a}                     => write!(f, "sin({})", a),
             NodeBase::FunctionCos             {a}                     => write!(f, "cos({})", a),
             NodeBase::FunctionTan             {a}                     => write!(f, "tan({})", a),
@@ -0,0 +1,289 @@
+use super::data;
+use super::interpreter;
+use super::paths;
+use super::ordering;
+use super::layers;
+use super::defaults;
+use super::fitting::{add, sub, scale, dot, cross, unit};
+
+
+
+// Function for moving closed paths out by half the kerf of their layer, so parts come out the size they are drawn.
+// Outer contours grow and holes shrink, going by how many closed paths each one is inside. Open paths are left alone.
+// Returns the number of paths that vanished because they were too small for the kerf.
+pub fn compensate(data : &interpreter::InterpreterData, paths : &mut paths::Paths) -> usize {
+    let mut kerfs = vec![defaults::KERF; paths.equations.len()];
+    for group in layers::groups(data) {
+        for equation in group.equations {
+            kerfs[equation] = group.layer.kerf;
+        }
+    }
+    let outers      = ordering::containers(&paths.paths);
+    let mut removed = 0;
+    let mut res     = vec![];
+    for (path, outers) in paths.paths.iter().zip(outers.iter()) {
+        let kerf = kerfs[path.equation];
+        if ! path.closed || kerf.width == 0.0 {
+            res.push(path.clone());
+            continue;
+        }
+        let distance = if outers.len() % 2 == 0 { kerf.width / 2.0 } else { - kerf.width / 2.0 };
+        let loops    = offset(&path.points, distance, kerf.join);
+        if loops.is_empty() {
+            removed += 1;
+        }
+        for points in loops {
+            res.push(paths::Path {
+                equation : path.equation,
+                closed   : true,
+                points   : points
+            });
+        }
+    }
+    paths.paths = res;
+    return removed;
+}
+
+
+
+// Function for offsetting a loop by `distance`, outwards when positive and inwards when negative.
+// The offset edges are joined around corners, then split where they cross themselves,
+// keeping only the loops that run the same way as the original and stay clear of it.
+// Loops with no area are returned as they are.
+pub fn offset(points : &[data::Point], distance : f64, join : data::Join) -> Vec<Vec<data::Point>> {
+    let mut corners = points.to_vec();
+    corners.dedup_by(|a, b| a == b);
+    if corners.len() > 1 && corners[0] == corners[corners.len() - 1] {
+        corners.pop();
+    }
+    let area = area(&corners);
+    if corners.len() < 3 || area == 0.0 {
+        return vec![points.to_vec()];
+    }
+    // Work anticlockwise, where the outside of every edge is on its right.
+    if area < 0.0 {
+        corners.reverse();
+    }
+
+    let count   = corners.len();
+    let mut raw = vec![];
+    for index in 0..count {
+        let before = corners[(index + count - 1) % count];
+        let corner = corners[index];
+        let after  = corners[(index + 1) % count];
+        join_corner(&mut raw, before, corner, after, distance, join);
+    }
+
+    let mut res = vec![];
+    for mut points in split(raw) {
+        if keep(&points, &corners, distance) {
+            if area < 0.0 {
+                points.reverse();
+            }
+            points.push(points[0]);
+            res.push(points);
+        }
+    }
+    return res;
+}
+
+
+
+// Add the offset points around a corner, between the edge from `before` and the edge to `after`.
+fn join_corner(res : &mut Vec<data::Point>, before : data::Point, corner : data::Point, after : data::Point, distance : f64, join : data::Join) {
+    let incoming   = unit(sub(corner, before));
+    let outgoing   = unit(sub(after, corner));
+    let normal_in  = data::Point {x : incoming.y, y : - incoming.x};
+    let normal_out = data::Point {x : outgoing.y, y : - outgoing.x};
+    let first      = add(corner, scale(normal_in, distance));
+    let last       = add(corner, scale(normal_out, distance));
+    let turn       = cross(incoming, outgoing);
+    let cosine     = dot(normal_in, normal_out);
+
+    // Where the offset edges overlap they are cut back to where they cross.
+    // Edges turning almost straight back have no useful crossing, and are left for `split` to untangle.
+    if turn * distance <= 0.0 {
+        if 1.0 + cosine > 1e-6 {
+            res.push(add(corner, scale(add(normal_in, normal_out), distance / (1.0 + cosine))));
+        } else {
+            res.push(first);
+            res.push(corner);
+            res.push(last);
+        }
+        return;
+    }
+
+    // Where the offset edges leave a gap it is filled by the join.
+    match join {
+        data::Join::Miter => {
+            // The mitred corner is `1 / cos(half the turn)` times the distance from the corner.
+            if 2.0 / (1.0 + cosine) <= data::OFFSET_MITER_LIMIT * data::OFFSET_MITER_LIMIT {
+                res.push(add(corner, scale(add(normal_in, normal_out), distance / (1.0 + cosine))));
+            } else {
+                res.push(first);
+                res.push(last);
+            }
+        },
+        data::Join::Round => {
+            let sweep = cross(normal_in, normal_out).atan2(cosine);
+            let steps = (sweep.abs() / data::OFFSET_ROUND_STEP).ceil().max(1.0) as i32;
+            for step in 0..=steps {
+                let angle = sweep * step as f64 / steps as f64;
+                let (sin, cos) = angle.sin_cos();
+                let normal     = data::Point {
+                    x : normal_in.x * cos - normal_in.y * sin,
+                    y : normal_in.x * sin + normal_in.y * cos
+                };
+                res.push(add(corner, scale(normal, distance)));
+            }
+        }
+    }
+}
+
+
+
+// Split a loop into loops that do not cross themselves, cutting it at each crossing in turn.
+fn split(points : Vec<data::Point>) -> Vec<Vec<data::Point>> {
+    let mut res   = vec![];
+    let mut stack = vec![points];
+    while let Some(points) = stack.pop() {
+        match crossing(&points) {
+            Some((first, second, at)) => {
+                // The part between the crossing edges, and the rest of the loop around it.
+                let mut inner = vec![at];
+                inner.extend_from_slice(&points[(first + 1)..=second]);
+                let mut outer = points[..=first].to_vec();
+                outer.push(at);
+                outer.extend_from_slice(&points[(second + 1)..]);
+                stack.push(inner);
+                stack.push(outer);
+            },
+            None => res.push(points)
+        }
+    }
+    return res;
+}
+
+
+
+// Find the first two edges of a loop that cross, and where they cross.
+// Edges sharing a corner are not checked, as they always meet there.
+fn crossing(points : &[data::Point]) -> Option<(usize, usize, data::Point)> {
+    let count = points.len();
+    if count < 4 {
+        return None;
+    }
+    for first in 0..(count - 2) {
+        let a = points[first];
+        let b = points[first + 1];
+        let (lo, hi) = (a.x.min(b.x), a.x.max(b.x));
+        // The last edge shares a corner with the first.
+        let end = if first == 0 { count - 1 } else { count };
+        for second in (first + 2)..end {
+            let c = points[second];
+            let d = points[(second + 1) % count];
+            if c.x.max(d.x) < lo || c.x.min(d.x) > hi {
+                continue;
+            }
+            if let Some(at) = intersection(a, b, c, d) {
+                return Some((first, second, at));
+            }
+        }
+    }
+    return None;
+}
+
+
+
+// Get where the segment from `a` to `b` crosses the segment from `c` to `d`, if it does.
+fn intersection(a : data::Point, b : data::Point, c : data::Point, d : data::Point) -> Option<data::Point> {
+    let ab    = sub(b, a);
+    let cd    = sub(d, c);
+    let denom = cross(ab, cd);
+    if denom == 0.0 {
+        return None;
+    }
+    let ac = sub(c, a);
+    let t  = cross(ac, cd) / denom;
+    let u  = cross(ac, ab) / denom;
+    if t <= 0.0 || t >= 1.0 || u <= 0.0 || u >= 1.0 {
+        return None;
+    }
+    return Some(add(a, scale(ab, t)));
+}
+
+
+
+// Check if a loop split from an offset belongs in the result.
+// Parts where the offset folded back on itself run the other way, and parts which are closer
+// to the original loop than the offset only appear where it was too narrow to offset.
+fn keep(points : &[data::Point], corners : &[data::Point], distance : f64) -> bool {
+    let signed = area(points);
+    if signed <= 0.0 {
+        return false;
+    }
+    let clearance = distance.abs() * 0.999;
+    for point in points.iter() {
+        for index in 0..corners.len() {
+            let next = corners[(index + 1) % corners.len()];
+            if paths::point_segment_distance(*point, corners[index], next) < clearance {
+                return false;
+            }
+        }
+    }
+    return true;
+}
+
+
+
+// Get the signed area of a loop given without its closing point, positive when it runs anticlockwise.
+fn area(points : &[data::Point]) -> f64 {
+    let mut res = 0.0;
+    for index in 0..points.len() {
+        res += cross(points[index], points[(index + 1) % points.len()]);
+    }
+    return res / 2.0;
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::run;
+    use super::super::lexer;
+    use super::super::parser;
+
+    // Offset the paths of a script, returning the furthest each path's points are from `centre`.
+    fn radii(script : &str, centres : &[data::Point]) -> Vec<f64> {
+        let (data, mut paths) = run::trace_text(script, 1);
+        compensate(&data, &mut paths);
+        return paths.paths.iter().map(|path| {
+            let centre = centres[path.equation];
+            path.points.iter().map(|point| (point.x - centre.x).hypot(point.y - centre.y)).fold(0.0, f64::max)
+        }).collect();
+    }
+
+    #[test]
+    fn kerf_is_set_per_layer() {
+        let centres = [data::Point {x : -7.0, y : 0.0}, data::Point {x : 0.0, y : 0.0}, data::Point {x : 7.0, y : 0.0}, data::Point {x : 0.0, y : 7.0}];
+        let script  = "#frame(-10, -10, 20, 20)\n#resolution(400, 400)\n#kerf(0.4)\n(x + 7) * (x + 7) + y * y = 4\n\
+                       #layer(\"Wide\", \"cut\", 90, 300, 1)\n#kerf(1)\nx * x + y * y = 4\n\
+                       #layer(\"Narrow\", \"cut\", 90, 300, 1)\n#kerf(0.2, \"round\")\n(x - 7) * (x - 7) + y * y = 4\n\
+                       #layer(\"Wide\", \"cut\", 90, 300, 1)\nx * x + (y - 7) * (y - 7) = 4\n";
+        let radii   = radii(script, &centres);
+        assert_eq!(radii.len(), 4);
+        for (radius, expected) in radii.iter().zip([2.2, 2.5, 2.1, 2.5]) {
+            assert!((radius - expected).abs() < 0.01, "Radius {} should be {}.", radius, expected);
+        }
+    }
+
+    #[test]
+    fn kerf_can_not_change_after_equations() {
+        let lexer_res  = lexer::lex("test.lrn".to_string(), "#layer(\"Wide\", \"cut\", 90, 300, 1)\n#kerf(1)\ny = x\n#kerf(2)\n".to_string());
+        let parser_res = parser::parse(lexer_res.tokens);
+        let res        = interpreter::interpret(parser_res.nodes, None);
+        assert!(! res.success);
+        assert_eq!(res.exceptions[0].message, "The kerf of layer `Wide` can not be changed after its equations.");
+    }
+}
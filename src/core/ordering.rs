@@ -0,0 +1,393 @@
+use super::data;
+use super::paths;
+
+
+
+// Travel distance of a job before and after its paths are ordered, in units.
+#[derive(Clone, Copy, Debug)]
+pub struct TravelReport {
+    pub before : f64,
+    pub after  : f64
+}
+
+
+
+// Function for ordering paths to cut with the least travel between them, starting from `start`.
+// Paths are picked nearest first, then pairs are swapped while that shortens the travel (2-opt).
+// Closed paths inside other closed paths are always cut first, so parts are not cut free before their holes.
+// Cutting holes first wins over travel: the order given is kept if it already cuts holes first and is no longer,
+// and otherwise the new order is used, even when its travel is longer.
+pub fn optimise(paths : &mut paths::Paths, start : data::Point, ordering : data::Ordering) -> TravelReport {
+    let before  = travel(&paths.paths, start);
+    let reverse = ordering == data::Ordering::Reverse;
+    let outers  = containers(&paths.paths);
+
+    // Pick the nearest path which has no uncut paths inside it.
+    let mut inside   = vec![0; paths.paths.len()];
+    for list in outers.iter() {
+        for outer in list.iter() {
+            inside[*outer] += 1;
+        }
+    }
+    let mut remaining = paths.paths.clone();
+    let mut done      = vec![false; remaining.len()];
+    let mut ids       = vec![];
+    let mut res       = vec![];
+    let mut position  = start;
+    while ids.len() < remaining.len() {
+        let mut best = None;
+        for index in 0..remaining.len() {
+            if done[index] || inside[index] > 0 {
+                continue;
+            }
+            let (distance, entry) = entry(&remaining[index], position, reverse);
+            if best.is_none_or(|(d, _, _)| distance < d) {
+                best = Some((distance, index, entry));
+            }
+        }
+        let (_, index, entry) = best.unwrap();
+        let mut path = remaining[index].clone();
+        enter(&mut path, entry);
+        position = path.points[path.points.len() - 1];
+        done[index] = true;
+        for outer in outers[index].iter() {
+            inside[*outer] -= 1;
+        }
+        ids.push(index);
+        res.push(path);
+    }
+    remaining.clear();
+
+    if res.len() <= data::ORDERING_MAX_PATHS {
+        two_opt(&mut res, &mut ids, &outers, start, reverse);
+    }
+    let holes_first = outers.iter().enumerate().all(|(inner, list)| list.iter().all(|outer| *outer > inner));
+    // Of two orders which both cut holes first, the shorter is kept.
+    if travel(&res, start) < before || ! holes_first {
+        paths.paths = res;
+    }
+    return TravelReport {
+        before : before,
+        after  : travel(&paths.paths, start)
+    };
+}
+
+
+
+// Get the total distance travelled between paths, starting from `start`.
+pub fn travel(paths : &[paths::Path], start : data::Point) -> f64 {
+    let mut res      = 0.0;
+    let mut position = start;
+    for path in paths.iter() {
+        res     += distance(position, path.points[0]);
+        position = path.points[path.points.len() - 1];
+    }
+    return res;
+}
+
+
+
+// Find the closed paths containing each path. Only closed paths can contain others.
+pub fn containers(paths : &[paths::Path]) -> Vec<Vec<usize>> {
+    let mut res = vec![vec![]; paths.len()];
+    for (inner, path) in paths.iter().enumerate() {
+        if ! path.closed {
+            continue;
+        }
+        let (lo, hi) = bounds(&path.points);
+        for (outer, other) in paths.iter().enumerate() {
+            if outer == inner || ! other.closed {
+                continue;
+            }
+            let (other_lo, other_hi) = bounds(&other.points);
+            let within = other_lo.x <= lo.x && other_lo.y <= lo.y && hi.x <= other_hi.x && hi.y <= other_hi.y;
+            if within && (lo, hi) != (other_lo, other_hi) && contains(&other.points, path.points[0]) {
+                res[inner].push(outer);
+            }
+        }
+    }
+    return res;
+}
+
+
+
+// Ways of starting a path.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Entry {
+    Forward,
+    Backward,
+    // Start a loop at one of its points.
+    At {
+        index : usize
+    }
+}
+
+
+
+// Find the nearest way into a path from a position. Paths are only entered forwards unless `reverse` is set.
+fn entry(path : &paths::Path, position : data::Point, reverse : bool) -> (f64, Entry) {
+    let forward = distance(position, path.points[0]);
+    if ! reverse {
+        return (forward, Entry::Forward);
+    }
+    if path.closed {
+        let mut best = (forward, Entry::Forward);
+        for (index, point) in path.points.iter().enumerate() {
+            let d = distance(position, *point);
+            if d < best.0 {
+                best = (d, Entry::At {index : index});
+            }
+        }
+        return best;
+    }
+    let backward = distance(position, path.points[path.points.len() - 1]);
+    return if backward < forward { (backward, Entry::Backward) } else { (forward, Entry::Forward) };
+}
+
+
+
+// Change a path to start where it is entered.
+fn enter(path : &mut paths::Path, entry : Entry) {
+    match entry {
+        Entry::Forward       => (),
+        Entry::Backward      => path.points.reverse(),
+        Entry::At {index}    => {
+            // The first and last points of a loop are the same, so the loop is rebuilt around the new start.
+            let count  = path.points.len() - 1;
+            let mut points = path.points[index..count].to_vec();
+            points.extend_from_slice(&path.points[..=index]);
+            path.points = points;
+        }
+    }
+}
+
+
+
+// Reverse runs of paths while that shortens the travel, keeping every path after the closed paths inside it.
+// When `reverse` is set the paths in a run are also turned around, so the travel inside the run is unchanged.
+// Each run is tried in constant time, so a pass over every pair of paths takes time proportional to the pairs.
+fn two_opt(paths : &mut [paths::Path], ids : &mut [usize], outers : &[Vec<usize>], start : data::Point, reverse : bool) {
+    let count        = paths.len();
+    let mut position = vec![0; count];
+    for (index, id) in ids.iter().enumerate() {
+        position[*id] = index;
+    }
+    let mut gaps       = Gaps::new(paths);
+    let mut containers = first_containers(ids, outers, &position);
+    for _ in 0..data::ORDERING_PASSES {
+        let mut improved = false;
+        for first in 0..count {
+            // First place a container of a path in the run is cut, as the run can not be reversed past it.
+            let mut container = count;
+            for last in (first + 1)..count {
+                container = container.min(containers[first].min(containers[last]));
+                if container <= last || change(paths, &gaps, first, last, start, reverse) >= -1e-9 {
+                    continue;
+                }
+                paths[first..=last].reverse();
+                ids[first..=last].reverse();
+                for index in first..=last {
+                    position[ids[index]] = index;
+                    if reverse {
+                        paths[index].points.reverse();
+                    }
+                }
+                gaps       = Gaps::new(paths);
+                containers = first_containers(ids, outers, &position);
+                container  = containers[first..=last].iter().fold(count, |a, b| a.min(*b));
+                improved   = true;
+            }
+        }
+        if ! improved {
+            break;
+        }
+    }
+}
+
+
+
+// Travel between neighbouring paths in order, and as it would be with the two paths swapped, summed from the first path
+// so the travel inside any run of paths is found from its ends.
+struct Gaps {
+    forward  : Vec<f64>,
+    backward : Vec<f64>
+}
+impl Gaps {
+    fn new(paths : &[paths::Path]) -> Gaps {
+        let mut res = Gaps {
+            forward  : vec![0.0],
+            backward : vec![0.0]
+        };
+        for pair in paths.windows(2) {
+            res.forward.push(res.forward[res.forward.len() - 1] + distance(tail(&pair[0]), head(&pair[1])));
+            res.backward.push(res.backward[res.backward.len() - 1] + distance(tail(&pair[1]), head(&pair[0])));
+        }
+        return res;
+    }
+}
+
+
+
+// Find the first place after each path that one of its containers is cut, or the number of paths if it has none.
+fn first_containers(ids : &[usize], outers : &[Vec<usize>], position : &[usize]) -> Vec<usize> {
+    return ids.iter().map(|id| outers[*id].iter().map(|outer| position[*outer]).fold(ids.len(), usize::min)).collect();
+}
+
+
+
+// Get the change in travel from reversing the run of paths from `first` to `last`.
+fn change(paths : &[paths::Path], gaps : &Gaps, first : usize, last : usize, start : data::Point, reverse : bool) -> f64 {
+    let before = if first == 0 { start } else { tail(&paths[first - 1]) };
+    let after  = paths.get(last + 1).map(head);
+    if reverse {
+        // Only the ends of the run change, as the paths inside are turned around with it.
+        let old = distance(before, head(&paths[first])) + after.map_or(0.0, |a| distance(tail(&paths[last]), a));
+        let new = distance(before, tail(&paths[last])) + after.map_or(0.0, |a| distance(head(&paths[first]), a));
+        return new - old;
+    }
+    let old = distance(before, head(&paths[first])) + after.map_or(0.0, |a| distance(tail(&paths[last]), a)) + gaps.forward[last] - gaps.forward[first];
+    let new = distance(before, head(&paths[last])) + after.map_or(0.0, |a| distance(tail(&paths[first]), a)) + gaps.backward[last] - gaps.backward[first];
+    return new - old;
+}
+
+
+
+// Get the first and last points of a path.
+fn head(path : &paths::Path) -> data::Point {
+    return path.points[0];
+}
+
+fn tail(path : &paths::Path) -> data::Point {
+    return path.points[path.points.len() - 1];
+}
+
+
+
+// Get the corners of the box around some points.
+fn bounds(points : &[data::Point]) -> (data::Point, data::Point) {
+    let mut lo = points[0];
+    let mut hi = points[0];
+    for point in points.iter() {
+        lo = data::Point {x : lo.x.min(point.x), y : lo.y.min(point.y)};
+        hi = data::Point {x : hi.x.max(point.x), y : hi.y.max(point.y)};
+    }
+    return (lo, hi);
+}
+
+
+
+// Check if a point is inside a loop, by counting how many of its edges a line to the right of the point crosses.
+pub fn contains(points : &[data::Point], point : data::Point) -> bool {
+    let mut res = false;
+    for pair in points.windows(2) {
+        let (a, b) = (pair[0], pair[1]);
+        if (a.y > point.y) != (b.y > point.y) && point.x < a.x + (point.y - a.y) / (b.y - a.y) * (b.x - a.x) {
+            res = ! res;
+        }
+    }
+    return res;
+}
+
+
+
+fn distance(a : data::Point, b : data::Point) -> f64 {
+    return (a.x - b.x).hypot(a.y - b.y);
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Pseudo-random numbers from 0 to 1, the same on every run.
+    fn random(seed : &mut u64) -> f64 {
+        *seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        return (*seed >> 11) as f64 / (1u64 << 53) as f64;
+    }
+
+    // Scattered open strokes, and squares with smaller squares inside them.
+    fn scattered(seed : &mut u64) -> Vec<paths::Path> {
+        let mut res = vec![];
+        for _ in 0..60 {
+            let start = data::Point {x : random(seed) * 100.0, y : random(seed) * 100.0};
+            res.push(paths::Path {
+                equation : 0,
+                closed   : false,
+                points   : vec![start, data::Point {x : start.x + random(seed) * 10.0, y : start.y + random(seed) * 10.0}]
+            });
+        }
+        for _ in 0..10 {
+            let corner = data::Point {x : random(seed) * 100.0, y : random(seed) * 100.0};
+            for size in [8.0, 4.0] {
+                let (x, y) = (corner.x + (8.0 - size) / 2.0, corner.y + (8.0 - size) / 2.0);
+                res.push(paths::Path {
+                    equation : 0,
+                    closed   : true,
+                    points   : vec![
+                        data::Point {x : x, y : y},
+                        data::Point {x : x + size, y : y},
+                        data::Point {x : x + size, y : y + size},
+                        data::Point {x : x, y : y + size},
+                        data::Point {x : x, y : y}
+                    ]
+                });
+            }
+        }
+        return res;
+    }
+
+    #[test]
+    fn never_lengthens_holes_first_order() {
+        let mut seed = 7;
+        let start    = data::Point {x : 0.0, y : 0.0};
+        for _ in 0..20 {
+            let input = scattered(&mut seed);
+            // Both a random order and an order which is already short, as nearest first may not improve on it.
+            let mut ordered = input.clone();
+            ordered.sort_by(|a, b| (a.points[0].x + a.points[0].y).partial_cmp(&(b.points[0].x + b.points[0].y)).unwrap());
+            for order in [input, ordered] {
+                for ordering in [data::Ordering::Order, data::Ordering::Reverse] {
+                    let mut paths = paths::Paths {
+                        frame     : paths::Frame {x : 0.0, y : 0.0, w : 110.0, h : 110.0},
+                        equations : vec!["".to_string()],
+                        paths     : order.clone()
+                    };
+                    let report = optimise(&mut paths, start, ordering);
+                    assert_eq!(report.before, travel(&order, start));
+                    assert_eq!(report.after, travel(&paths.paths, start));
+                    assert!(report.after <= report.before, "Travel went from {} to {}.", report.before, report.after);
+                    // Every hole is still cut before the square around it.
+                    for (inner, list) in containers(&paths.paths).iter().enumerate() {
+                        assert!(list.iter().all(|outer| *outer > inner));
+                    }
+                }
+            }
+        }
+    }
+
+    // A square around its hole, with the square first, which is shorter than cutting the hole first.
+    #[test]
+    fn holes_first_wins_over_travel() {
+        let square = |lo : f64, hi : f64| paths::Path {
+            equation : 0,
+            closed   : true,
+            points   : vec![
+                data::Point {x : lo, y : lo},
+                data::Point {x : hi, y : lo},
+                data::Point {x : hi, y : hi},
+                data::Point {x : lo, y : hi},
+                data::Point {x : lo, y : lo}
+            ]
+        };
+        let start     = data::Point {x : 0.0, y : 0.0};
+        let mut paths = paths::Paths {
+            frame     : paths::Frame {x : 0.0, y : 0.0, w : 10.0, h : 10.0},
+            equations : vec!["".to_string()],
+            paths     : vec![square(0.0, 10.0), square(4.0, 6.0)]
+        };
+        let report = optimise(&mut paths, start, data::Ordering::Order);
+        assert!(report.after > report.before, "Travel went from {} to {}.", report.before, report.after);
+        assert_eq!(bounds(&paths.paths[0].points), (data::Point {x : 4.0, y : 4.0}, data::Point {x : 6.0, y : 6.0}));
+    }
+}
@@ -0,0 +1,326 @@
+use super::data;
+use super::interpreter;
+use super::paths;
+use super::renderer;
+use super::exporter;
+use super::fitting;
+use super::style;
+
+
+
+// Points in an inch, the unit of PDF and PostScript pages.
+const POINTS_PER_INCH : f64 = 72.0;
+
+
+
+// Something drawn on a page, in points from the lower left corner.
+enum Mark {
+    Path {
+        start    : data::Point,
+        segments : Vec<fitting::Segment>,
+        closed   : bool,
+        width    : f64,
+        colour   : data::Colour,
+        // Lengths of dashes and gaps in turn, empty for a solid line.
+        dash     : Vec<f64>
+    },
+    Text {
+        at   : data::Point,
+        size : f64,
+        text : String
+    }
+}
+
+
+
+// Page the size of the frame, holding everything to draw on it.
+struct Page {
+    width  : f64,
+    height : f64,
+    marks  : Vec<Mark>
+}
+impl Page {
+    // Lay out the paths in the styles of their equations, and the axes if `#axes` is set, on a page the physical size of the frame.
+    fn new(data : &interpreter::InterpreterData, paths : &paths::Paths) -> Page {
+        let frame = paths.frame;
+        let scale = data.units.millimetres() / 25.4 * POINTS_PER_INCH;
+        let place = |point : &data::Point| data::Point {
+            x : (point.x - frame.x) * scale,
+            y : (point.y - frame.y) * scale
+        };
+
+        let mut res = Page {
+            width  : frame.w * scale,
+            height : frame.h * scale,
+            marks  : vec![]
+        };
+        for path in paths.paths.iter() {
+            res.marks.push(Mark::Path {
+                start    : place(&path.points[0]),
+                segments : fitting::fit_cubics(&path.points, fitting::stage_tolerance(data)).iter().map(|s| s.map(|p| place(&p))).collect(),
+                closed   : path.closed,
+                width    : style::width(data, path.equation) * scale,
+                colour   : style::colour(data, path.equation).0,
+                dash     : data.styles[path.equation].dash.iter().map(|length| length * scale).collect()
+            });
+        }
+        // The axes are labelled in frame units, so they match the equations when `#size` scales the frame.
+        if data.axes {
+            let size = data.scale();
+            res.axes(&paths::Frame {
+                x : frame.x / size,
+                y : frame.y / size,
+                w : frame.w / size,
+                h : frame.h / size
+            }, &|point : &data::Point| place(&data::Point {x : point.x * size, y : point.y * size}));
+        }
+        return res;
+    }
+
+    // Draw an axis through zero on each direction, or along the edge of the frame if zero is outside it,
+    // with a labelled tick at every step.
+    fn axes<F : Fn(&data::Point) -> data::Point>(&mut self, frame : &paths::Frame, place : &F) {
+        let step  = tick_step(frame.w.max(frame.h));
+        let size  = self.width.min(self.height) * 0.025;
+        let tick  = size * 0.5;
+        let width = size * 0.05;
+        let x     = 0.0f64.clamp(frame.x, frame.x + frame.w);
+        let y     = 0.0f64.clamp(frame.y, frame.y + frame.h);
+
+        self.line(place(&data::Point {x : frame.x, y : y}), place(&data::Point {x : frame.x + frame.w, y : y}), width);
+        self.line(place(&data::Point {x : x, y : frame.y}), place(&data::Point {x : x, y : frame.y + frame.h}), width);
+
+        let mut value = (frame.x / step).ceil() * step;
+        while value <= frame.x + frame.w {
+            let at   = place(&data::Point {x : value, y : y});
+            let text = exporter::number(value, 6);
+            self.line(data::Point {x : at.x, y : at.y - tick}, data::Point {x : at.x, y : at.y + tick}, width);
+            // Labels are centred below the tick, guessing half an em for each character.
+            self.marks.push(Mark::Text {
+                at   : data::Point {x : at.x - text.len() as f64 * size * 0.25, y : at.y - tick - size},
+                size : size,
+                text : text
+            });
+            value += step;
+        }
+        let mut value = (frame.y / step).ceil() * step;
+        while value <= frame.y + frame.h {
+            let at   = place(&data::Point {x : x, y : value});
+            let text = exporter::number(value, 6);
+            self.line(data::Point {x : at.x - tick, y : at.y}, data::Point {x : at.x + tick, y : at.y}, width);
+            // Zero is already labelled on the x axis.
+            if value != 0.0 {
+                self.marks.push(Mark::Text {
+                    at   : data::Point {x : at.x + tick * 1.5, y : at.y - size * 0.35},
+                    size : size,
+                    text : text
+                });
+            }
+            value += step;
+        }
+    }
+
+    // Draw a straight line.
+    fn line(&mut self, start : data::Point, end : data::Point, width : f64) {
+        self.marks.push(Mark::Path {
+            start    : start,
+            segments : vec![fitting::Segment::Line {to : end}],
+            closed   : false,
+            width    : width,
+            colour   : data::Colour {r : 0.0, g : 0.0, b : 0.0, a : 1.0},
+            dash     : vec![]
+        });
+    }
+}
+
+
+
+// Function for writing paths as a one page PDF document.
+pub fn export_pdf(data : &interpreter::InterpreterData, paths : &paths::Paths) -> renderer::RendererResult {
+    return exporter::write(&data.export, pdf_document(data, paths));
+}
+
+
+
+// Build the document written by `export_pdf`.
+pub fn pdf_document(data : &interpreter::InterpreterData, paths : &paths::Paths) -> String {
+    let page = Page::new(data, paths);
+
+    let mut content = vec!["1 J 1 j".to_string()];
+    // The dash pattern is kept until it is changed, so it is only set when it differs from the last path's.
+    let mut current = &vec![];
+    for mark in page.marks.iter() {
+        match mark {
+            Mark::Path {start, segments, closed, width, colour, dash} => {
+                if dash != current {
+                    content.push(format!("[{}] 0 d", lengths(dash)));
+                    current = dash;
+                }
+                let mut line = format!("{} RG {} w {} m", rgb(colour), number(*width), position(start));
+                for segment in segments.iter() {
+                    line += &match segment {
+                        fitting::Segment::Cubic {control1, control2, to} => format!(" {} {} {} c", position(control1), position(control2), position(to)),
+                        // Arcs are only fitted for G-code.
+                        fitting::Segment::Line  {to}                     |
+                        fitting::Segment::Arc   {to, ..}                 => format!(" {} l", position(to))
+                    };
+                }
+                line += if *closed { " h S" } else { " S" };
+                content.push(line);
+            },
+            Mark::Text {at, size, text}                               => {
+                content.push(format!("BT /F1 {} Tf {} Td ({}) Tj ET", number(*size), position(at), escape(text)));
+            }
+        }
+    }
+    let content = content.join("\n");
+
+    let objects = [
+        "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+        "<< /Type /Pages /Kids [3 0 R] /Count 1 >>".to_string(),
+        format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Resources << /Font << /F1 5 0 R >> >> /Contents 4 0 R >>",
+            number(page.width), number(page.height)
+        ),
+        // The end of line before `endstream` is not part of the stream.
+        format!("<< /Length {} >>\nstream\n{}\nendstream", content.len(), content),
+        "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica >>".to_string()
+    ];
+
+    // The cross reference table gives the byte offset of every object.
+    let mut res     = "%PDF-1.4\n".to_string();
+    let mut offsets = vec![];
+    for (index, object) in objects.iter().enumerate() {
+        offsets.push(res.len());
+        res += &format!("{} 0 obj\n{}\nendobj\n", index + 1, object);
+    }
+    let start = res.len();
+    res += &format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+    for offset in offsets {
+        res += &format!("{:010} 00000 n \n", offset);
+    }
+    res += &format!("trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n", objects.len() + 1, start);
+
+    return res;
+}
+
+
+
+// Function for writing paths as an encapsulated PostScript page.
+pub fn export_eps(data : &interpreter::InterpreterData, paths : &paths::Paths) -> renderer::RendererResult {
+    return exporter::write(&data.export, eps_document(data, paths));
+}
+
+
+
+// Build the page written by `export_eps`.
+pub fn eps_document(data : &interpreter::InterpreterData, paths : &paths::Paths) -> String {
+    let page = Page::new(data, paths);
+
+    let mut res = vec![
+        "%!PS-Adobe-3.0 EPSF-3.0".to_string(),
+        format!("%%BoundingBox: 0 0 {} {}", page.width.ceil() as i64, page.height.ceil() as i64),
+        format!("%%HiResBoundingBox: 0 0 {} {}", number(page.width), number(page.height)),
+        "%%Creator: lrinser".to_string(),
+        "%%Pages: 1".to_string(),
+        "%%EndComments".to_string(),
+        "1 setlinecap 1 setlinejoin".to_string()
+    ];
+    let mut current = &vec![];
+    for mark in page.marks.iter() {
+        match mark {
+            Mark::Path {start, segments, closed, width, colour, dash} => {
+                if dash != current {
+                    res.push(format!("[{}] 0 setdash", lengths(dash)));
+                    current = dash;
+                }
+                let mut line = format!("{} setrgbcolor {} setlinewidth newpath {} moveto", rgb(colour), number(*width), position(start));
+                for segment in segments.iter() {
+                    line += &match segment {
+                        fitting::Segment::Cubic {control1, control2, to} => format!(" {} {} {} curveto", position(control1), position(control2), position(to)),
+                        // Arcs are only fitted for G-code.
+                        fitting::Segment::Line  {to}                     |
+                        fitting::Segment::Arc   {to, ..}                 => format!(" {} lineto", position(to))
+                    };
+                }
+                line += if *closed { " closepath stroke" } else { " stroke" };
+                res.push(line);
+            },
+            Mark::Text {at, size, text}                               => {
+                res.push(format!("/Helvetica findfont {} scalefont setfont {} moveto ({}) show", number(*size), position(at), escape(text)));
+            }
+        }
+    }
+    res.push("showpage".to_string());
+    res.push("%%EOF".to_string());
+
+    return res.join("\n") + "\n";
+}
+
+
+
+// Get the distance between ticks on the axes, picking 1, 2 or 5 times a power of ten to give about ten ticks.
+fn tick_step(span : f64) -> f64 {
+    let rough = span / 10.0;
+    let power = 10.0f64.powf(rough.log10().floor());
+    for multiple in [1.0, 2.0, 5.0] {
+        if multiple * power >= rough {
+            return multiple * power;
+        }
+    }
+    return 10.0 * power;
+}
+
+
+
+// Format a length in points.
+fn number(value : f64) -> String {
+    return exporter::number(value, 3);
+}
+
+
+
+// Format a position in points.
+fn position(point : &data::Point) -> String {
+    return format!("{} {}", number(point.x), number(point.y));
+}
+
+
+
+// Format lengths in points, separated by spaces.
+fn lengths(values : &[f64]) -> String {
+    return values.iter().map(|value| number(*value)).collect::<Vec<String>>().join(" ");
+}
+
+
+
+// Format a colour as red, green and blue from 0 to 1.
+fn rgb(colour : &data::Colour) -> String {
+    return format!("{} {} {}", number(colour.r as f64), number(colour.g as f64), number(colour.b as f64));
+}
+
+
+
+// Escape text for a PDF or PostScript string.
+fn escape(text : &str) -> String {
+    return text.replace('\\', "\\\\").replace('(', "\\(").replace(')', "\\)");
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::run;
+
+    #[test]
+    fn stream_length_matches() {
+        let (data, paths) = run::trace_text("#frame(-5, -5, 10, 10)\nx * x + y * y = 9\n", 1);
+        let text          = pdf_document(&data, &paths);
+
+        let length = text.split("/Length ").nth(1).unwrap().split(' ').next().unwrap().parse::<usize>().unwrap();
+        let start  = text.find("stream\n").unwrap() + "stream\n".len();
+        let end    = text.find("\nendstream").unwrap();
+        assert_eq!(length, end - start);
+    }
+}
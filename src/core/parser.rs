@@ -17,7 +17,7 @@ pub struct ParserResult {
 #[derive(Clone, Debug)]
 pub struct HeaderArgsResult {
     pub success   : bool,
-    pub args      : Vec<i32>,
+    pub args      : Vec<f64>,
     pub exception : exceptions::ParserException
 }
 
@@ -88,7 +88,8 @@ impl Parser {
 
 
             // If header, parse header function. Else, parse expression.
-            if self.token.name == tokens::TK_HEADER {
+            let header = self.token.name == tokens::TK_HEADER;
+            if header {
                 res = self.header();
             } else {
                 res = self.expression();
@@ -98,6 +99,14 @@ impl Parser {
             }
             // Add new node.
             nodes.append(&mut res.nodes.clone());
+            // Style attributes may follow an equation.
+            if ! header && self.token.name == tokens::TK_LBRACKET {
+                let res = self.attributes();
+                if ! res.success {
+                    return res;
+                }
+                nodes.append(&mut res.nodes.clone());
+            }
             // Next line.
             if self.token.name != tokens::TK_EOL {
                 return ParserResult {
@@ -157,10 +166,30 @@ impl Parser {
         // Idenfify header function and get arguments.
         let res = match func.as_str() {
 
-            "frame"      => self.header_frame(),
-            "resolution" => self.header_resolution(),
-            "export"     => self.header_export(),
-            "print_now"  => self.header_print_now(),
+            "frame"            => self.header_frame(),
+            "resolution"       => self.header_resolution(),
+            "export"           => self.header_export(),
+            "print_now"        => self.header_print_now(),
+            "division_by_zero" => self.header_division_by_zero(),
+            "thickness"        => self.header_thickness(),
+            "antialias"        => self.header_antialias(),
+            "units"            => self.header_units(),
+            "power"            => self.header_power(),
+            "speed"            => self.header_speed(),
+            "passes"           => self.header_passes(),
+            "origin"           => self.header_origin(),
+            "raster"           => self.header_raster(),
+            "overscan"         => self.header_overscan(),
+            "skip_blank"       => self.header_skip_blank(),
+            "axes"             => self.header_axes(),
+            "tolerance"        => self.header_tolerance(),
+            "optimise_travel"  => self.header_optimise_travel(),
+            "kerf"             => self.header_kerf(),
+            "hatch"            => self.header_hatch(),
+            "layer"            => self.header_layer(),
+            "machine"          => self.header_machine(),
+            "size"             => self.header_size(),
+            "dpi"              => self.header_dpi(),
 
             _       => return self.failure(exceptions::ParserException {
                 base    : exceptions::ParserExceptionBase::InternalException,
@@ -189,16 +218,16 @@ impl Parser {
     fn header_frame(&mut self) -> ParserResult {
         let range = self.token.range.clone();
         // Get 4 number arguments.
-        let res = self.header_get_args(4);
+        let res = self.header_get_args(4, true);
         if ! res.success {
             return self.failure(res.exception);
         }
         return self.success(vec![nodes::Node {
             base : nodes::NodeBase::HeaderFuncFrame {
-                x : res.args[0],
-                y : res.args[1],
-                w : res.args[2],
-                h : res.args[3]
+                x : res.args[0] as i32,
+                y : res.args[1] as i32,
+                w : res.args[2] as i32,
+                h : res.args[3] as i32
             },
             range : data::Range {
                 filename : range.filename,
@@ -213,14 +242,14 @@ impl Parser {
     fn header_resolution(&mut self) -> ParserResult {
         let range = self.token.range.clone();
         // Get 2 number arguments.
-        let res = self.header_get_args(2);
+        let res = self.header_get_args(2, true);
         if ! res.success {
             return self.failure(res.exception);
         }
         return self.success(vec![nodes::Node {
             base : nodes::NodeBase::HeaderFuncResolution {
-                w : res.args[0],
-                h : res.args[1]
+                w : res.args[0] as i32,
+                h : res.args[1] as i32
             },
             range : data::Range {
                 filename : range.filename,
@@ -275,27 +304,651 @@ impl Parser {
     }
 
 
+    // Division by zero header function found.
+    fn header_division_by_zero(&mut self) -> ParserResult {
+        let range = self.token.range.clone();
+        // Get 1 string argument.
+        if self.token.name != tokens::TK_STRING {
+            return self.failure(
+                exceptions::ParserException {
+                    base    : exceptions::ParserExceptionBase::MissingTokenException,
+                    message : "Expected (String) not found.".to_string(),
+                    range   : self.token.range.clone()
+                }
+            );
+        }
+        let level = self.token.value.clone();
+        self.advance();
+
+        return self.success(vec![nodes::Node {
+            base : nodes::NodeBase::HeaderFuncDivisionByZero {
+                level : level
+            },
+            range : data::Range {
+                filename : range.filename,
+                start    : range.start,
+                end      : self.token.range.end
+            }
+        }]);
+    }
+
+
+    // Thickness header function found.
+    fn header_thickness(&mut self) -> ParserResult {
+        let range = self.token.range.clone();
+        // Get 1 number argument.
+        let res = self.header_get_args(1, false);
+        if ! res.success {
+            return self.failure(res.exception);
+        }
+        return self.success(vec![nodes::Node {
+            base : nodes::NodeBase::HeaderFuncThickness {
+                thickness : res.args[0]
+            },
+            range : data::Range {
+                filename : range.filename,
+                start    : range.start,
+                end      : self.token.range.end
+            }
+        }]);
+    }
+
+
+    // Antialias header function found.
+    fn header_antialias(&mut self) -> ParserResult {
+        let range = self.token.range.clone();
+
+        return self.success(vec![nodes::Node {
+            base : nodes::NodeBase::HeaderFuncAntialias,
+            range : data::Range {
+                filename : range.filename,
+                start    : range.start,
+                end      : self.token.range.end
+            }
+        }]);
+    }
+
+
+    // Units header function found.
+    fn header_units(&mut self) -> ParserResult {
+        let range = self.token.range.clone();
+        // Get 1 string argument.
+        if self.token.name != tokens::TK_STRING {
+            return self.failure(
+                exceptions::ParserException {
+                    base    : exceptions::ParserExceptionBase::MissingTokenException,
+                    message : "Expected (String) not found.".to_string(),
+                    range   : self.token.range.clone()
+                }
+            );
+        }
+        let units = self.token.value.clone();
+        self.advance();
+
+        return self.success(vec![nodes::Node {
+            base : nodes::NodeBase::HeaderFuncUnits {
+                units : units
+            },
+            range : data::Range {
+                filename : range.filename,
+                start    : range.start,
+                end      : self.token.range.end
+            }
+        }]);
+    }
+
+
+    // Power header function found.
+    fn header_power(&mut self) -> ParserResult {
+        let range = self.token.range.clone();
+        // Get 1 number argument.
+        let res = self.header_get_args(1, false);
+        if ! res.success {
+            return self.failure(res.exception);
+        }
+        return self.success(vec![nodes::Node {
+            base : nodes::NodeBase::HeaderFuncPower {
+                power : res.args[0]
+            },
+            range : data::Range {
+                filename : range.filename,
+                start    : range.start,
+                end      : self.token.range.end
+            }
+        }]);
+    }
+
+
+    // Speed header function found.
+    fn header_speed(&mut self) -> ParserResult {
+        let range = self.token.range.clone();
+        // Get 1 number argument.
+        let res = self.header_get_args(1, false);
+        if ! res.success {
+            return self.failure(res.exception);
+        }
+        return self.success(vec![nodes::Node {
+            base : nodes::NodeBase::HeaderFuncSpeed {
+                speed : res.args[0]
+            },
+            range : data::Range {
+                filename : range.filename,
+                start    : range.start,
+                end      : self.token.range.end
+            }
+        }]);
+    }
+
+
+    // Passes header function found.
+    fn header_passes(&mut self) -> ParserResult {
+        let range = self.token.range.clone();
+        // Get 1 integer argument.
+        let res = self.header_get_args(1, true);
+        if ! res.success {
+            return self.failure(res.exception);
+        }
+        return self.success(vec![nodes::Node {
+            base : nodes::NodeBase::HeaderFuncPasses {
+                passes : res.args[0] as i32
+            },
+            range : data::Range {
+                filename : range.filename,
+                start    : range.start,
+                end      : self.token.range.end
+            }
+        }]);
+    }
+
+
+    // Origin header function found.
+    fn header_origin(&mut self) -> ParserResult {
+        let range = self.token.range.clone();
+        // Get 2 number arguments.
+        let res = self.header_get_args(2, false);
+        if ! res.success {
+            return self.failure(res.exception);
+        }
+        return self.success(vec![nodes::Node {
+            base : nodes::NodeBase::HeaderFuncOrigin {
+                x : res.args[0],
+                y : res.args[1]
+            },
+            range : data::Range {
+                filename : range.filename,
+                start    : range.start,
+                end      : self.token.range.end
+            }
+        }]);
+    }
+
+
+    // Raster header function found.
+    fn header_raster(&mut self) -> ParserResult {
+        let range = self.token.range.clone();
+
+        return self.success(vec![nodes::Node {
+            base : nodes::NodeBase::HeaderFuncRaster,
+            range : data::Range {
+                filename : range.filename,
+                start    : range.start,
+                end      : self.token.range.end
+            }
+        }]);
+    }
+
+
+    // Overscan header function found.
+    fn header_overscan(&mut self) -> ParserResult {
+        let range = self.token.range.clone();
+        // Get 1 number argument.
+        let res = self.header_get_args(1, false);
+        if ! res.success {
+            return self.failure(res.exception);
+        }
+        return self.success(vec![nodes::Node {
+            base : nodes::NodeBase::HeaderFuncOverscan {
+                overscan : res.args[0]
+            },
+            range : data::Range {
+                filename : range.filename,
+                start    : range.start,
+                end      : self.token.range.end
+            }
+        }]);
+    }
+
+
+    // Skip blank header function found.
+    fn header_skip_blank(&mut self) -> ParserResult {
+        let range = self.token.range.clone();
+
+        return self.success(vec![nodes::Node {
+            base : nodes::NodeBase::HeaderFuncSkipBlank,
+            range : data::Range {
+                filename : range.filename,
+                start    : range.start,
+                end      : self.token.range.end
+            }
+        }]);
+    }
+
+
+    // Axes header function found.
+    fn header_axes(&mut self) -> ParserResult {
+        let range = self.token.range.clone();
+
+        return self.success(vec![nodes::Node {
+            base : nodes::NodeBase::HeaderFuncAxes,
+            range : data::Range {
+                filename : range.filename,
+                start    : range.start,
+                end      : self.token.range.end
+            }
+        }]);
+    }
+
+
+    // Tolerance header function found.
+    fn header_tolerance(&mut self) -> ParserResult {
+        let range = self.token.range.clone();
+        // Get 1 number argument.
+        let res = self.header_get_args(1, false);
+        if ! res.success {
+            return self.failure(res.exception);
+        }
+        return self.success(vec![nodes::Node {
+            base : nodes::NodeBase::HeaderFuncTolerance {
+                tolerance : res.args[0]
+            },
+            range : data::Range {
+                filename : range.filename,
+                start    : range.start,
+                end      : self.token.range.end
+            }
+        }]);
+    }
+
+
+    // Optimise travel header function found.
+    fn header_optimise_travel(&mut self) -> ParserResult {
+        let range = self.token.range.clone();
+        // Get 1 string argument.
+        if self.token.name != tokens::TK_STRING {
+            return self.failure(
+                exceptions::ParserException {
+                    base    : exceptions::ParserExceptionBase::MissingTokenException,
+                    message : "Expected (String) not found.".to_string(),
+                    range   : self.token.range.clone()
+                }
+            );
+        }
+        let mode = self.token.value.clone();
+        self.advance();
+
+        return self.success(vec![nodes::Node {
+            base : nodes::NodeBase::HeaderFuncOptimiseTravel {
+                mode : mode
+            },
+            range : data::Range {
+                filename : range.filename,
+                start    : range.start,
+                end      : self.token.range.end
+            }
+        }]);
+    }
+
+
+    // Kerf header function found.
+    fn header_kerf(&mut self) -> ParserResult {
+        let range = self.token.range.clone();
+        // Get 1 number argument, and an optional string argument.
+        let res = self.header_get_args(1, false);
+        if ! res.success {
+            return self.failure(res.exception);
+        }
+        let mut join = "miter".to_string();
+        if self.token.name == tokens::TK_COMMA {
+            self.advance();
+            if self.token.name != tokens::TK_STRING {
+                return self.failure(
+                    exceptions::ParserException {
+                        base    : exceptions::ParserExceptionBase::MissingTokenException,
+                        message : "Expected (String) not found.".to_string(),
+                        range   : self.token.range.clone()
+                    }
+                );
+            }
+            join = self.token.value.clone();
+            self.advance();
+        }
+
+        return self.success(vec![nodes::Node {
+            base : nodes::NodeBase::HeaderFuncKerf {
+                width : res.args[0],
+                join  : join
+            },
+            range : data::Range {
+                filename : range.filename,
+                start    : range.start,
+                end      : self.token.range.end
+            }
+        }]);
+    }
+
+
+    // Hatch header function found.
+    fn header_hatch(&mut self) -> ParserResult {
+        let range = self.token.range.clone();
+        // Get 2 number arguments, and any number of string arguments.
+        let res = self.header_get_args(2, false);
+        if ! res.success {
+            return self.failure(res.exception);
+        }
+        let mut options = vec![];
+        while self.token.name == tokens::TK_COMMA {
+            self.advance();
+            if self.token.name != tokens::TK_STRING {
+                return self.failure(
+                    exceptions::ParserException {
+                        base    : exceptions::ParserExceptionBase::MissingTokenException,
+                        message : "Expected (String) not found.".to_string(),
+                        range   : self.token.range.clone()
+                    }
+                );
+            }
+            options.push(self.token.value.clone());
+            self.advance();
+        }
+
+        return self.success(vec![nodes::Node {
+            base : nodes::NodeBase::HeaderFuncHatch {
+                spacing : res.args[0],
+                angle   : res.args[1],
+                options : options
+            },
+            range : data::Range {
+                filename : range.filename,
+                start    : range.start,
+                end      : self.token.range.end
+            }
+        }]);
+    }
+
+
+    // Layer header function found.
+    fn header_layer(&mut self) -> ParserResult {
+        let range = self.token.range.clone();
+        // Get 2 string arguments, 2 number arguments and 1 integer argument.
+        let mut names = vec![];
+        for _ in 0..2 {
+            if self.token.name != tokens::TK_STRING {
+                return self.failure(
+                    exceptions::ParserException {
+                        base    : exceptions::ParserExceptionBase::MissingTokenException,
+                        message : "Expected (String) not found.".to_string(),
+                        range   : self.token.range.clone()
+                    }
+                );
+            }
+            names.push(self.token.value.clone());
+            self.advance();
+            if self.token.name != tokens::TK_COMMA {
+                return self.failure(
+                    exceptions::ParserException {
+                        base    : exceptions::ParserExceptionBase::MissingTokenException,
+                        message : "Expected (Comma) not found.".to_string(),
+                        range   : self.token.range.clone()
+                    }
+                );
+            }
+            self.advance();
+        }
+        let settings = self.header_get_args(2, false);
+        if ! settings.success {
+            return self.failure(settings.exception);
+        }
+        if self.token.name != tokens::TK_COMMA {
+            return self.failure(
+                exceptions::ParserException {
+                    base    : exceptions::ParserExceptionBase::MissingTokenException,
+                    message : "Expected (Comma) not found.".to_string(),
+                    range   : self.token.range.clone()
+                }
+            );
+        }
+        self.advance();
+        let passes = self.header_get_args(1, true);
+        if ! passes.success {
+            return self.failure(passes.exception);
+        }
+
+        return self.success(vec![nodes::Node {
+            base : nodes::NodeBase::HeaderFuncLayer {
+                name   : names[0].clone(),
+                mode   : names[1].clone(),
+                power  : settings.args[0],
+                speed  : settings.args[1],
+                passes : passes.args[0] as i32
+            },
+            range : data::Range {
+                filename : range.filename,
+                start    : range.start,
+                end      : self.token.range.end
+            }
+        }]);
+    }
+
+
+    // Machine header function found.
+    fn header_machine(&mut self) -> ParserResult {
+        let range = self.token.range.clone();
+        // Get 1 string argument.
+        if self.token.name != tokens::TK_STRING {
+            return self.failure(
+                exceptions::ParserException {
+                    base    : exceptions::ParserExceptionBase::MissingTokenException,
+                    message : "Expected (String) not found.".to_string(),
+                    range   : self.token.range.clone()
+                }
+            );
+        }
+        let name = self.token.value.clone();
+        self.advance();
+
+        return self.success(vec![nodes::Node {
+            base : nodes::NodeBase::HeaderFuncMachine {
+                name : name
+            },
+            range : data::Range {
+                filename : range.filename,
+                start    : range.start,
+                end      : self.token.range.end
+            }
+        }]);
+    }
+
+
+    // Size header function found.
+    fn header_size(&mut self) -> ParserResult {
+        let range = self.token.range.clone();
+        // Get 2 number arguments.
+        let res = self.header_get_args(2, false);
+        if ! res.success {
+            return self.failure(res.exception);
+        }
+        return self.success(vec![nodes::Node {
+            base : nodes::NodeBase::HeaderFuncSize {
+                w : res.args[0],
+                h : res.args[1]
+            },
+            range : data::Range {
+                filename : range.filename,
+                start    : range.start,
+                end      : self.token.range.end
+            }
+        }]);
+    }
+
+
+    // Dpi header function found.
+    fn header_dpi(&mut self) -> ParserResult {
+        let range = self.token.range.clone();
+        // Get 1 number argument.
+        let res = self.header_get_args(1, false);
+        if ! res.success {
+            return self.failure(res.exception);
+        }
+        return self.success(vec![nodes::Node {
+            base : nodes::NodeBase::HeaderFuncDpi {
+                dpi : res.args[0]
+            },
+            range : data::Range {
+                filename : range.filename,
+                start    : range.start,
+                end      : self.token.range.end
+            }
+        }]);
+    }
+
+
+    // Style attributes found after an equation.
+    fn attributes(&mut self) -> ParserResult {
+        let range = self.token.range.clone();
+        self.advance();
+        let mut colour = None;
+        let mut width  = None;
+        let mut dash   = None;
+        loop {
+            // Look for attribute name.
+            if self.token.name != tokens::TK_ATTRIBUTE {
+                return self.failure(exceptions::ParserException {
+                    base    : exceptions::ParserExceptionBase::MissingTokenException,
+                    message : "Expected (Attribute) not found.".to_string(),
+                    range   : self.token.range.clone()
+                });
+            }
+            let attribute = self.token.clone();
+            let given     = match attribute.value.as_str() {
+                "colour" => colour.is_some(),
+                "width"  => width.is_some(),
+                _        => dash.is_some()
+            };
+            if given {
+                return self.failure(exceptions::ParserException {
+                    base    : exceptions::ParserExceptionBase::IllegalTokenException,
+                    message : format!("Attribute `{}` was already given.", attribute.value),
+                    range   : attribute.range
+                });
+            }
+            self.advance();
+            // Look for opening parenthesis.
+            if self.token.name != tokens::TK_LPAREN {
+                return self.failure(exceptions::ParserException {
+                    base    : exceptions::ParserExceptionBase::MissingTokenException,
+                    message : "Expected (LeftParen) not found.".to_string(),
+                    range   : self.token.range.clone()
+                });
+            }
+            self.advance();
+            // Get 1 string argument for colour, 1 number argument for width, and 1 or more number arguments for dash.
+            match attribute.value.as_str() {
+                "colour" => {
+                    if self.token.name != tokens::TK_STRING {
+                        return self.failure(exceptions::ParserException {
+                            base    : exceptions::ParserExceptionBase::MissingTokenException,
+                            message : "Expected (String) not found.".to_string(),
+                            range   : self.token.range.clone()
+                        });
+                    }
+                    colour = Some(self.token.value.clone());
+                    self.advance();
+                },
+                "width"  => {
+                    let res = self.header_get_args(1, false);
+                    if ! res.success {
+                        return self.failure(res.exception);
+                    }
+                    width = Some(res.args[0]);
+                },
+                _        => {
+                    let mut lengths = vec![];
+                    loop {
+                        let res = self.header_get_args(1, false);
+                        if ! res.success {
+                            return self.failure(res.exception);
+                        }
+                        lengths.push(res.args[0]);
+                        if self.token.name != tokens::TK_COMMA {
+                            break;
+                        }
+                        self.advance();
+                    }
+                    dash = Some(lengths);
+                }
+            }
+            // Look for closing parenthesis.
+            if self.token.name != tokens::TK_RPAREN {
+                return self.failure(exceptions::ParserException {
+                    base    : exceptions::ParserExceptionBase::MissingTokenException,
+                    message : "Expected (RightParen) not found.".to_string(),
+                    range   : self.token.range.clone()
+                });
+            }
+            self.advance();
+            if self.token.name != tokens::TK_COMMA {
+                break;
+            }
+            self.advance();
+        }
+        // Look for closing bracket.
+        if self.token.name != tokens::TK_RBRACKET {
+            return self.failure(exceptions::ParserException {
+                base    : exceptions::ParserExceptionBase::MissingTokenException,
+                message : "Expected (RightBracket) not found.".to_string(),
+                range   : self.token.range.clone()
+            });
+        }
+        let end = self.token.range.end;
+        self.advance();
+
+        return self.success(vec![nodes::Node {
+            base : nodes::NodeBase::Style {
+                colour : colour,
+                width  : width,
+                dash   : dash
+            },
+            range : data::Range {
+                filename : range.filename,
+                start    : range.start,
+                end      : end
+            }
+        }]);
+    }
+
+
     // Get correct number of header function arguments, separated by commas.
-    fn header_get_args(&mut self, arg_count : usize) -> HeaderArgsResult {
-        let mut args  : Vec<i32> = vec![];
+    // If `integer` is set, arguments with a decimal point are not allowed.
+    fn header_get_args(&mut self, arg_count : usize, integer : bool) -> HeaderArgsResult {
+        let mut args  : Vec<f64> = vec![];
         for i in 0..arg_count {
-            let mut multiplier : i32 = 1;
+            let mut multiplier : f64 = 1.0;
             if self.token.name == tokens::TK_SUBTRACT {
-                multiplier = -1;
+                multiplier = -1.0;
                 self.advance();
             }
-            if self.token.name != tokens::TK_NUMBER {
+            if self.token.name != tokens::TK_NUMBER || (integer && self.token.value.contains('.')) {
                 return HeaderArgsResult {
                     success   : false,
                     args      : vec![],
                     exception : exceptions::ParserException {
                         base    : exceptions::ParserExceptionBase::MissingTokenException,
-                        message : "Expected (Integer, Minus) not found.".to_string(),
+                        message : if integer { "Expected (Integer, Minus) not found." } else { "Expected (Number, Minus) not found." }.to_string(),
                         range   : self.token.range.clone()
                     }
                 };
             }
-            args.push(self.token.value.parse::<i32>().unwrap() * multiplier);
+            args.push(self.token.value.parse::<f64>().unwrap() * multiplier);
             self.advance();
             if i < arg_count - 1 {
                 if self.token.name != tokens::TK_COMMA {
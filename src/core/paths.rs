@@ -0,0 +1,246 @@
+use serde::{Serialize, Deserialize};
+
+use super::data;
+use super::exceptions;
+use super::interpreter;
+use super::tracer;
+use super::fitting;
+
+
+
+// Rectangle that paths are clipped to, in frame units while tracing and in units on the bed once extracted.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Frame {
+    pub x : f64,
+    pub y : f64,
+    pub w : f64,
+    pub h : f64
+}
+
+
+
+// Polyline traced from an equation, in units on the bed. `equation` is the index of the equation in the script.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Path {
+    pub equation : usize,
+    pub closed   : bool,
+    pub points   : Vec<data::Point>
+}
+
+
+
+// Vector form of a script, made of every path traced from its equations.
+// `equations` holds the source of each equation, in script order.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Paths {
+    pub frame     : Frame,
+    pub equations : Vec<String>,
+    pub paths     : Vec<Path>
+}
+impl Paths {
+    // Serialise to JSON text.
+    pub fn to_json(&self) -> String {
+        return serde_json::to_string_pretty(self).unwrap();
+    }
+
+    // Read from JSON text written by `to_json`.
+    #[cfg(test)]
+    pub fn from_json(text : &str) -> Result<Paths, String> {
+        return serde_json::from_str(text).map_err(|e| e.to_string());
+    }
+}
+
+
+
+// Success/Failure identification class.
+#[derive(Clone, Debug)]
+pub struct PathsResult {
+    pub success   : bool,
+    pub paths     : Paths,
+    pub exception : exceptions::RendererException
+}
+
+
+
+// Function for tracing every equation into paths clipped to the frame, using `threads` threads.
+// Curves are split wherever they are discontinuous or leave the frame, scaled onto the bed by `#size`,
+// and simplified under the `#tolerance`.
+pub fn extract(data : &interpreter::InterpreterData, threads : usize) -> PathsResult {
+    let frame     = Frame {
+        x : data.position.x as f64,
+        y : data.position.y as f64,
+        w : data.size.x as f64,
+        h : data.size.y as f64
+    };
+    let mut paths = Paths {
+        frame     : data.frame(),
+        equations : vec![],
+        paths     : vec![]
+    };
+    let tolerance = fitting::stage_tolerance(data);
+    let scale     = data.scale();
+    let place     = |points : &[data::Point]| fitting::simplify(&points.iter().map(|point| data::Point {
+        x : point.x * scale,
+        y : point.y * scale
+    }).collect::<Vec<data::Point>>(), tolerance);
+    for (index, equation) in data.equations.iter().enumerate() {
+        let res = tracer::trace(equation, data, threads);
+        if ! res.success {
+            return PathsResult {
+                success   : false,
+                paths     : paths,
+                exception : res.exception
+            };
+        }
+        paths.equations.push(format!("{}", equation));
+        for curve in res.curves {
+            let closed = curve[0] == curve[curve.len() - 1];
+            let pieces = clip(&curve, &frame);
+            // A loop which is not cut by the frame stays closed.
+            if closed && pieces.len() == 1 && pieces[0].len() == curve.len() {
+                paths.paths.push(Path {
+                    equation : index,
+                    closed   : true,
+                    points   : place(&curve)
+                });
+                continue;
+            }
+            for points in pieces {
+                paths.paths.push(Path {
+                    equation : index,
+                    closed   : false,
+                    points   : place(&points)
+                });
+            }
+        }
+    }
+    return PathsResult {
+        success   : true,
+        paths     : paths,
+        exception : exceptions::RendererException {
+            base    : exceptions::RendererExceptionBase::NoException,
+            message : "".to_string(),
+            range   : data::Range {
+                filename : "".to_string(),
+                start    : 0,
+                end      : 0
+            }
+        }
+    };
+}
+
+
+
+// Cut a polyline into the pieces inside the frame.
+// A loop cut by the frame has its first and last pieces joined, as they meet at the start of the loop.
+fn clip(points : &[data::Point], frame : &Frame) -> Vec<Vec<data::Point>> {
+    let lo = data::Point {x : frame.x, y : frame.y};
+    let hi = data::Point {x : frame.x + frame.w, y : frame.y + frame.h};
+    let mut pieces  : Vec<Vec<data::Point>> = vec![];
+    let mut current : Vec<data::Point>      = vec![];
+    for pair in points.windows(2) {
+        let (a, b) = (pair[0], pair[1]);
+        match clip_segment(a, b, lo, hi) {
+            Some((t0, t1)) => {
+                if t0 > 0.0 && current.len() > 0 {
+                    pieces.push(current);
+                    current = vec![];
+                }
+                if current.len() == 0 {
+                    current.push(lerp(a, b, t0));
+                }
+                current.push(lerp(a, b, t1));
+                if t1 < 1.0 {
+                    pieces.push(current);
+                    current = vec![];
+                }
+            },
+            None           => {
+                if current.len() > 0 {
+                    pieces.push(current);
+                    current = vec![];
+                }
+            }
+        }
+    }
+    if current.len() > 0 {
+        pieces.push(current);
+    }
+    pieces.retain(|piece| piece.len() >= 2 && piece.iter().any(|point| *point != piece[0]));
+
+    let closed = points.len() > 1 && points[0] == points[points.len() - 1];
+    if closed && pieces.len() > 1 && pieces[0][0] == points[0] && pieces[pieces.len() - 1][pieces[pieces.len() - 1].len() - 1] == points[0] {
+        let first = pieces.remove(0);
+        let last  = pieces.len() - 1;
+        pieces[last].extend(first.into_iter().skip(1));
+    }
+    return pieces;
+}
+
+
+
+// Point part of the way along a segment.
+fn lerp(a : data::Point, b : data::Point, t : f64) -> data::Point {
+    if t <= 0.0 {
+        return a;
+    }
+    if t >= 1.0 {
+        return b;
+    }
+    return data::Point {
+        x : a.x + (b.x - a.x) * t,
+        y : a.y + (b.y - a.y) * t
+    };
+}
+
+
+
+// Find the part of a segment inside a box between `lo` and `hi`, as fractions of the way along it.
+// Returns none if the segment misses the box.
+pub fn clip_segment(a : data::Point, b : data::Point, lo : data::Point, hi : data::Point) -> Option<(f64, f64)> {
+    let mut t0 = 0.0;
+    let mut t1 = 1.0;
+    for (p, q) in [(a.x - b.x, a.x - lo.x), (b.x - a.x, hi.x - a.x), (a.y - b.y, a.y - lo.y), (b.y - a.y, hi.y - a.y)] {
+        if p == 0.0 {
+            if q < 0.0 {
+                return None;
+            }
+        } else if p < 0.0 {
+            t0 = (q / p).max(t0);
+        } else {
+            t1 = (q / p).min(t1);
+        }
+    }
+    if t0 > t1 {
+        return None;
+    }
+    return Some((t0, t1));
+}
+
+
+
+// Get the distance from a point to a segment.
+pub fn point_segment_distance(p : data::Point, a : data::Point, b : data::Point) -> f64 {
+    let dx     = b.x - a.x;
+    let dy     = b.y - a.y;
+    let length = dx * dx + dy * dy;
+    let t      = if length > 0.0 { (((p.x - a.x) * dx + (p.y - a.y) * dy) / length).clamp(0.0, 1.0) } else { 0.0 };
+    return (p.x - (a.x + t * dx)).hypot(p.y - (a.y + t * dy));
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::run;
+
+    #[test]
+    fn json_round_trip() {
+        let data = run::interpret_text("#frame(-5, -5, 10, 10)\nx * x + y * y = 9\ny = x\n");
+        let res  = extract(&data, 1);
+        assert!(res.success, "{}", res.exception);
+        assert!(res.paths.paths.len() >= 2);
+        assert_eq!(Paths::from_json(&res.paths.to_json()).unwrap(), res.paths);
+    }
+}
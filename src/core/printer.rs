@@ -33,7 +33,7 @@ pub fn print(filename : String) -> PrinterResult {
 fn print_windows(filename : String) -> PrinterResult {
     match process::Command::new("cmd")
         //.args(&["/C", format!("mspaint /pt \"{}\\{}\"", env::current_dir().unwrap().display(), filename).as_str()])
-        .args(&["/C", format!("mspaint /pt {}", filename).as_str()])
+        .args(["/C", format!("mspaint /pt {}", filename).as_str()])
         .output()
     {
         Ok(value) => println!("OK {:?}", value),
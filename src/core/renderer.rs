@@ -1,9 +1,14 @@
-use image;
-use super::logger;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::thread;
+
+use png;
 use super::exceptions;
 use super::interpreter;
 use super::data;
-use super::nodes;
+use super::paths;
+use super::exporter;
+use super::style;
 
 
 
@@ -16,119 +21,408 @@ pub struct RendererResult {
 }
 
 
-// Start rendering.
-pub fn render(mut data : interpreter::InterpreterData) -> RendererResult {
-    // If resolution is not set, use frame size.
-    if data.resolution.x == 0 {
-        data.resolution.x = data.size.x
-    }
-    if data.resolution.y == 0 {
-        data.resolution.y = data.size.y
-    }
-
-    // Create image buffer.
-    let mut buffer = image::ImageBuffer::new(data.resolution.x as u32, data.resolution.y as u32);
-
-    // Get values for each equation at each x pixel.
-    // This is a Vec (each column) containing a Vec (each valid equation set) containing a LeftRight (left and right side values of the column)
-    let mut values : Vec<Vec<data::LeftRight>> = vec![]; 
-    for pixel_x in 0..data.resolution.x {
-        values.push(vec![]);
-        let x1 = data.position.x as f32 + (data.size.x as f32 * (pixel_x as f32 / data.resolution.x as f32));
-        let x2 = data.position.x as f32 + (data.size.x as f32 * ((pixel_x as f32 + 1.0) / data.resolution.x as f32));
-        for index in 0..data.equations.len() {
-            let equation = &data.equations[index];
-            let eq1       = equation.simplify(x1);
-            let eq2       = equation.simplify(x2);
-            if ! eq1.success {
-                return RendererResult {
-                    success         : false,
-                    export_filename : "".to_string(),
-                    exception       : eq1.exception
+// Part of a path to draw, in units, with the colour and half the width to draw it with.
+struct Segment {
+    a      : data::Point,
+    b      : data::Point,
+    colour : data::Colour,
+    radius : f64
+}
+
+
+
+// Stroke used to draw curves, in units.
+struct Stroke {
+    pixel     : data::Point,
+    antialias : bool
+}
+impl Stroke {
+    // Get how much of a pixel, between `lo` and `hi`, is covered by the stroke along a segment.
+    // Strokes thinner than a pixel are drawn one pixel wide.
+    fn coverage(&self, segment : &Segment, lo : data::Point, hi : data::Point) -> f64 {
+        if self.antialias {
+            let width  = self.pixel.x.max(self.pixel.y);
+            let radius = segment.radius.max(width / 2.0);
+            let centre = data::Point {
+                x : (lo.x + hi.x) / 2.0,
+                y : (lo.y + hi.y) / 2.0
+            };
+            return (0.5 + (radius - paths::point_segment_distance(centre, segment.a, segment.b)) / width).clamp(0.0, 1.0);
+        }
+        return if box_segment_distance(lo, hi, segment.a, segment.b) <= segment.radius { 1.0 } else { 0.0 };
+    }
+
+    // Distance around a segment that pixels may be touched.
+    fn reach(&self, segment : &Segment) -> f64 {
+        return segment.radius + self.pixel.x.max(self.pixel.y);
+    }
+}
+
+
+
+// Start rendering paths to a PNG image, using `threads` threads or one per core if zero.
+pub fn render(data : interpreter::InterpreterData, paths : &paths::Paths, threads : usize) -> RendererResult {
+    let file = match File::create(&data.export) {
+        Ok(file) => file,
+        Err(e)   => return exporter::failure(format!("Could not create `{}`: {}", data::escapify(data.export.clone()), e))
+    };
+    return match encode(&data, paths, threads, BufWriter::new(file)) {
+        Ok(_v) => exporter::success(data.export),
+        Err(e) => exporter::failure(format!("Could not write `{}`: {}", data::escapify(data.export.clone()), e))
+    };
+}
+
+
+
+// Encode the PNG image written by `render` into `writer`.
+// The image records its pixels per metre, from the size of the frame on the bed, so it prints at its physical size.
+// Rows are streamed to the writer as they are rendered.
+pub fn encode<W : Write>(data : &interpreter::InterpreterData, paths : &paths::Paths, threads : usize, writer : W) -> Result<(), String> {
+    // Write the image header.
+    let resolution  = data.pixel_resolution();
+    let mut encoder = png::Encoder::new(writer, resolution.x as u32, resolution.y as u32);
+    encoder.set_color(png::ColorType::RGBA);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_compression(png::Compression::Fast);
+    encoder.set_filter(png::FilterType::Sub);
+    let mut writer = match encoder.write_header().and_then(|mut writer| writer.write_chunk(png::chunk::pHYs, &physical_pixels(data)).map(|_v| writer)) {
+        Ok(writer) => writer,
+        Err(e)     => return Err(e.to_string())
+    };
+    let mut stream = writer.stream_writer();
+
+    return render_rows(data, paths, threads, true, |rows| {
+        return stream.write_all(rows).map_err(|e| e.to_string());
+    }).and_then(|_v| stream.finish().map_err(|e| e.to_string()));
+}
+
+
+
+// Render paths to RGBA rows, top to bottom, using `threads` threads or one per core if zero.
+// Paths are drawn with the width and dashes of their equation's style, in its colour if `coloured` is set and black otherwise.
+// The image is split into bands of rows which are rendered independently and handed to `sink` in order,
+// so the output does not depend on the thread count. Stops at the first error from `sink`.
+// Pixels are marked from the traced paths after offsetting, hatching and dashing rather than from interval bounds of the equations,
+// which do not describe those; the intervals are used while tracing, which is where gaps between points are closed.
+pub fn render_rows<F : FnMut(&[u8]) -> Result<(), String>>(data : &interpreter::InterpreterData, paths : &paths::Paths, threads : usize, coloured : bool, mut sink : F) -> Result<(), String> {
+    // Work out the axes of the resolution which are not set.
+    let mut data    = data.clone();
+    data.resolution = data.pixel_resolution();
+    let threads     = thread_count(threads);
+
+    let black        = data::Colour {
+        r : 0.0,
+        g : 0.0,
+        b : 0.0,
+        a : 1.0
+    };
+    let mut segments = vec![];
+    for path in paths.paths.iter() {
+        let style  = &data.styles[path.equation];
+        let colour = if coloured { style::colour(&data, path.equation).0 } else { black };
+        let radius = style.width.unwrap_or(data.thickness) / 2.0;
+        for dash in style::dashes(&path.points, &style.dash) {
+            for pair in dash.windows(2) {
+                segments.push(Segment {
+                    a      : pair[0],
+                    b      : pair[1],
+                    colour : colour,
+                    radius : radius
+                });
+            }
+        }
+    }
+    let frame  = data.frame();
+    let stroke = Stroke {
+        pixel     : data::Point {
+            x : frame.w / data.resolution.x as f64,
+            y : frame.h / data.resolution.y as f64
+        },
+        antialias : data.antialias
+    };
+
+    // Sort the segments into the bands of rows they might touch.
+    let band_count = (data.resolution.y as usize).div_ceil(data::RENDERER_BAND_HEIGHT as usize);
+    let mut bins   = vec![vec![]; band_count];
+    for (index, segment) in segments.iter().enumerate() {
+        let (a, b)       = (segment.a, segment.b);
+        let reach        = stroke.reach(segment);
+        let (start, end) = rows_touched(a.y.max(b.y) + reach, a.y.min(b.y) - reach, &data);
+        if start >= end {
+            continue;
+        }
+        for band in (start / data::RENDERER_BAND_HEIGHT)..((end - 1) / data::RENDERER_BAND_HEIGHT + 1) {
+            bins[band as usize].push(index);
+        }
+    }
+
+    // Render a window of bands at a time, one band per thread, and hand them to the sink in order.
+    // Only the window is kept in memory, so memory use does not grow with the image height.
+    let width      = data.resolution.x as usize;
+    let height     = data.resolution.y as usize;
+    let band_size  = width * 4 * data::RENDERER_BAND_HEIGHT as usize;
+    let mut window = vec![0u8; band_size * threads];
+    let mut top    = 0;
+    while top < height {
+        let rows   = (data::RENDERER_BAND_HEIGHT as usize * threads).min(height - top);
+        let pixels = &mut window[..(rows * width * 4)];
+        pixels.fill(255);
+        thread::scope(|scope| {
+            for (index, band) in pixels.chunks_mut(band_size).enumerate() {
+                let band_index = top / data::RENDERER_BAND_HEIGHT as usize + index;
+                let segments   = &segments;
+                let bin        = &bins[band_index];
+                let stroke     = &stroke;
+                let data       = &data;
+                scope.spawn(move || {
+                    render_band(segments, bin, band_index as i32 * data::RENDERER_BAND_HEIGHT, band, stroke, data);
+                });
+            }
+        });
+        sink(pixels)?;
+        top += rows;
+    }
+    return Ok(());
+}
+
+
+
+// Get the contents of a PNG `pHYs` chunk, giving the pixels per metre on each axis.
+fn physical_pixels(data : &interpreter::InterpreterData) -> Vec<u8> {
+    let resolution = data.pixel_resolution();
+    let frame      = data.frame();
+    let metres     = data.units.millimetres() / 1000.0;
+    let mut res    = vec![];
+    res.extend_from_slice(&((resolution.x as f64 / (frame.w * metres)).round() as u32).to_be_bytes());
+    res.extend_from_slice(&((resolution.y as f64 / (frame.h * metres)).round() as u32).to_be_bytes());
+    // The unit is the metre.
+    res.push(1);
+    return res;
+}
+
+
+
+// Get the number of threads to render with. Zero means one per core.
+pub fn thread_count(threads : usize) -> usize {
+    if threads > 0 {
+        return threads;
+    }
+    return match thread::available_parallelism() {
+        Ok(count) => count.get(),
+        Err(_e)   => 1
+    };
+}
+
+
+
+// Draw segments into a band of image rows, starting at image row `top`.
+// The coverage of each pixel is the most any one segment covers it, so joins between segments are not darker,
+// and the pixel takes the colour of that segment.
+fn render_band(segments : &[Segment], bin : &[usize], top : i32, band : &mut [u8], stroke : &Stroke, data : &interpreter::InterpreterData) {
+    let width        = data.resolution.x;
+    let frame        = data.frame();
+    let band_height  = (band.len() / (width as usize * 4)) as i32;
+    let mut coverage = vec![(0.0, None); band.len() / 4];
+    for segment in bin.iter().map(|index| &segments[*index]) {
+        let (a, b)       = (segment.a, segment.b);
+        let reach        = stroke.reach(segment);
+        let (start, end) = rows_touched(a.y.max(b.y) + reach, a.y.min(b.y) - reach, data);
+        let left         = ((a.x.min(b.x) - reach - frame.x) / stroke.pixel.x).floor().max(0.0) as i32;
+        let right        = ((a.x.max(b.x) + reach - frame.x) / stroke.pixel.x).ceil().min(width as f64) as i32;
+        for row in start.max(top)..end.min(top + band_height) {
+            for column in left..right {
+                let lo = data::Point {
+                    x : frame.x + column as f64 * stroke.pixel.x,
+                    y : frame.y + frame.h - (row + 1) as f64 * stroke.pixel.y
                 };
-            } else if ! eq2.success {
-                return RendererResult {
-                    success         : false,
-                    export_filename : "".to_string(),
-                    exception       : eq2.exception
+                let hi = data::Point {
+                    x : lo.x + stroke.pixel.x,
+                    y : lo.y + stroke.pixel.y
                 };
-            }
-            match eq1.value.base.clone() {
-                nodes::NodeBase::MultipleNumber {value : value1} => {
-                    match eq2.value.base.clone() {
-                        nodes::NodeBase::MultipleNumber {value : value2} => {
-                            let values_index = values.len() - 1;
-                            values[values_index].push(data::LeftRight {
-                                left  : value1,
-                                right : value2
-                            });
-                            continue;
-                        },
-                        _                                                 => {
-                            logger::error(format!("Failed to simplify: {}", eq2.value));
-                        }
-                    }
-                }
-                _                                        => {
-                    logger::error(format!("Failed to simplify: {}", eq1.value));
+                let index  = ((row - top) * width + column) as usize;
+                let amount = stroke.coverage(segment, lo, hi);
+                if amount > coverage[index].0 {
+                    coverage[index] = (amount, Some(segment.colour));
                 }
             }
         }
     }
 
-    // Draw equation values to image.
-    for (pixel_x, pixel_y_reversed, pixel) in buffer.enumerate_pixels_mut() {
-        let pixel_y = data.resolution.y - (pixel_y_reversed as i32);
-        let y1 = data.position.y as f32 + (data.size.y as f32 * (pixel_y as f32 / data.resolution.y as f32));
-        let y2 = data.position.y as f32 + (data.size.y as f32 * ((pixel_y as f32 + 1.0) / data.resolution.y as f32));
-
-        let mut colour   = data::Colour {
-            r : 1.0,
-            g : 1.0,
-            b : 1.0,
-            a : 1.0
+    // Blend the stroke colours over white.
+    for (index, (amount, colour)) in coverage.iter().enumerate() {
+        let pixel = match colour {
+            Some(colour) => {
+                let amount = amount * colour.a as f64;
+                [blend(colour.r, amount), blend(colour.g, amount), blend(colour.b, amount), 255]
+            },
+            None         => [255, 255, 255, 255]
         };
-        let mut done = false;
-        for leftright in values[pixel_x as usize].clone() {
-            for left in leftright.left.values.clone() {
-                for right in leftright.right.values.clone() {
-                    if (y1 >= left && y2 <= right) || (y1 <= left && y2 >= right) {
-                        colour.r = 0.0;
-                        colour.g = 0.0;
-                        colour.b = 0.0;
-                        done = true;
-                        break;
+        band[(index * 4)..(index * 4 + 4)].copy_from_slice(&pixel);
+    }
+}
+
+
+
+// Mix a colour channel over white.
+fn blend(channel : f32, amount : f64) -> u8 {
+    return (255.0 * (1.0 - amount) + channel as f64 * 255.0 * amount).round() as u8;
+}
+
+
+
+// Get the image rows, from `start` up to but not including `end`, between two y values on the bed.
+// Image rows run top to bottom, while y values run bottom to top.
+fn rows_touched(top : f64, bottom : f64, data : &interpreter::InterpreterData) -> (i32, i32) {
+    let frame     = data.frame();
+    let frame_top = frame.y + frame.h;
+    let pixel     = frame.h / data.resolution.y as f64;
+    let start     = ((frame_top - top) / pixel).floor().clamp(0.0, data.resolution.y as f64) as i32;
+    let end       = ((frame_top - bottom) / pixel).ceil().clamp(0.0, data.resolution.y as f64) as i32;
+    return (start, end);
+}
+
+
+
+// Get the distance from a box, between `lo` and `hi`, to a segment. Zero if they overlap.
+fn box_segment_distance(lo : data::Point, hi : data::Point, a : data::Point, b : data::Point) -> f64 {
+    if paths::clip_segment(a, b, lo, hi).is_some() {
+        return 0.0;
+    }
+
+    // Otherwise the closest points are on an end of the segment or a corner of the box.
+    let point_box = |p : data::Point| (p.x - p.x.clamp(lo.x, hi.x)).hypot(p.y - p.y.clamp(lo.y, hi.y));
+    let mut res = point_box(a).min(point_box(b));
+    for corner in [lo, hi, data::Point {x : lo.x, y : hi.y}, data::Point {x : hi.x, y : lo.y}] {
+        res = res.min(paths::point_segment_distance(corner, a, b));
+    }
+    return res;
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::run;
+
+    // Render a script into one RGBA image on `threads` threads, with its width and height.
+    fn image(script : &str, threads : usize) -> (Vec<u8>, usize, usize) {
+        let (data, paths) = run::trace_text(script, threads);
+        let resolution    = data.pixel_resolution();
+        let mut res       = vec![];
+        render_rows(&data, &paths, threads, false, |rows| {
+            res.extend_from_slice(rows);
+            return Ok(());
+        }).unwrap();
+        return (res, resolution.x as usize, resolution.y as usize);
+    }
+
+    // Count the groups of dark pixels which touch, including across corners.
+    fn components(pixels : &[u8], width : usize, height : usize) -> usize {
+        let dark     = |index : usize| pixels[index * 4] < 128;
+        let mut seen = vec![false; width * height];
+        let mut res  = 0;
+        for start in 0..(width * height) {
+            if seen[start] || !dark(start) {
+                continue;
+            }
+            res += 1;
+            seen[start]   = true;
+            let mut stack = vec![start];
+            while let Some(index) = stack.pop() {
+                let (x, y) = ((index % width) as i64, (index / width) as i64);
+                for (dx, dy) in [(-1, -1), (0, -1), (1, -1), (-1, 0), (1, 0), (-1, 1), (0, 1), (1, 1)] {
+                    let (nx, ny) = (x + dx, y + dy);
+                    if nx < 0 || ny < 0 || nx >= width as i64 || ny >= height as i64 {
+                        continue;
+                    }
+                    let next = ny as usize * width + nx as usize;
+                    if !seen[next] && dark(next) {
+                        seen[next] = true;
+                        stack.push(next);
                     }
                 }
-                if done {break}
             }
-            if done {break}
         }
+        return res;
+    }
 
-        *pixel = image::Rgba([(colour.r * 255.0) as u8, (colour.g * 255.0) as u8, (colour.b * 255.0) as u8, (colour.a * 255.0) as u8]);
+    // Shapes which double back and lines which are almost vertical used to be drawn with holes in them.
+    #[test]
+    fn curves_are_drawn_without_holes() {
+        for script in ["#frame(-10, -10, 20, 20)\n#resolution(200, 200)\nx * x + y * y = 16\n", "#frame(-10, -10, 20, 20)\n#resolution(200, 200)\ny = root(3, x)\n"] {
+            let (pixels, width, height) = image(script, 1);
+            assert_eq!(components(&pixels, width, height), 1, "{}", script);
+        }
     }
 
-    // Write image.
-    match buffer.save(data.export.clone()) {
-        Ok(_v)  => (),
-        Err(_e) => {
-            logger::critical("Image write failed.");
-            panic!();
+    // Bands are rendered independently, so the rows do not depend on how many threads drew them.
+    #[test]
+    fn rows_do_not_depend_on_threads() {
+        let script    = "#frame(-10, -10, 20, 20)\n#resolution(300, 400)\nx * x + y * y = 16\ny = tan(x) [width(0.5), dash(1, 0.5)]\ny = sin(x) * 3\n";
+        let (one, ..) = image(script, 1);
+        for threads in [2, 3, 8] {
+            let (many, ..) = image(script, threads);
+            assert!(one == many, "Rows differ on {} threads.", threads);
         }
-    };
+    }
 
-    // Return success.
-    return RendererResult {
-        success         : true,
-        export_filename : data.export,
-        exception       : exceptions::RendererException {
-            base    : exceptions::RendererExceptionBase::NoException,
-            message : "".to_string(),
-            range   : data::Range {
-                filename : "".to_string(),
-                start    : 0,
-                end      : 0
+    // Rows are handed over a window of one band per thread at a time, in order, with only the last window cut short.
+    #[test]
+    fn rows_are_streamed_by_band() {
+        let (data, paths) = run::trace_text("#frame(-10, -10, 20, 20)\n#resolution(50, 300)\nx * x + y * y = 16\n", 1);
+        let band          = data::RENDERER_BAND_HEIGHT as usize;
+        for threads in [1, 2, 3] {
+            let mut calls = vec![];
+            render_rows(&data, &paths, threads, false, |rows| {
+                assert_eq!(rows.len() % (50 * 4), 0);
+                calls.push(rows.len() / (50 * 4));
+                return Ok(());
+            }).unwrap();
+            let mut expected = vec![];
+            let mut top      = 0;
+            while top < 300 {
+                expected.push((band * threads).min(300 - top));
+                top += band * threads;
             }
+            assert_eq!(calls, expected, "Rows on {} threads.", threads);
         }
-    };
+
+        // The first error from the sink stops rendering.
+        let mut calls = 0;
+        let res       = render_rows(&data, &paths, 1, false, |_rows| {
+            calls += 1;
+            return Err("full".to_string());
+        });
+        assert_eq!((res, calls), (Err("full".to_string()), 1));
+    }
+
+    // Encode a script as a PNG image in memory, and read back its size and pixels per metre.
+    fn encoded(script : &str) -> (u32, u32, png::PixelDimensions) {
+        let (data, paths) = run::trace_text(script, 1);
+        let mut bytes     = vec![];
+        encode(&data, &paths, 1, &mut bytes).unwrap();
+        let (info, mut reader) = png::Decoder::new(&bytes[..]).read_info().unwrap();
+        let mut pixels         = vec![0; info.buffer_size()];
+        reader.next_frame(&mut pixels).unwrap();
+        return (info.width, info.height, reader.info().pixel_dims.unwrap());
+    }
+
+    #[test]
+    fn pixels_per_metre() {
+        // A 20 by 10 frame shown at 100 mm wide is 100 by 50 mm, at 254 dots per inch or 10 per millimetre.
+        let (width, height, dims) = encoded("#frame(0, 0, 20, 10)\n#size(100, 0)\n#dpi(254)\ny = x\n");
+        assert_eq!((width, height), (1000, 500));
+        assert_eq!((dims.xppu, dims.yppu, dims.unit), (10000, 10000, png::Unit::Meter));
+
+        // A resolution given for an axis is kept, and its pixels are stretched over the frame.
+        let (width, height, dims) = encoded("#frame(0, 0, 20, 10)\n#size(100, 0)\n#dpi(254)\n#resolution(300, 0)\ny = x\n");
+        assert_eq!((width, height), (300, 500));
+        assert_eq!((dims.xppu, dims.yppu), (3000, 10000));
+
+        // Frames in inches are 25.4 mm to the unit.
+        let (width, height, dims) = encoded("#units(\"in\")\n#frame(0, 0, 4, 2)\n#dpi(100)\ny = x\n");
+        assert_eq!((width, height), (400, 200));
+        assert_eq!((dims.xppu, dims.yppu), (3937, 3937));
+    }
 }
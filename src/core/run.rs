@@ -6,15 +6,100 @@ use super::data;
 use super::lexer;
 use super::parser;
 use super::interpreter;
+use super::paths;
+use super::offset;
+use super::hatch;
+use super::ordering;
+use super::layers;
+use super::gcode;
 use super::renderer;
+use super::exporter;
 use super::printer;
+use super::machine;
 
 
 
-// Function for easily running from filename. 
-pub fn run(filename: &str) {
+// Options given on the command line.
+// `threads` is the number of threads to render with, or zero for one per core.
+// `export` replaces the export filename set by the script, and `machine` the machine profile.
+#[derive(Clone, Debug)]
+pub struct Options {
+    pub threads : usize,
+    pub export  : Option<String>,
+    pub machine : Option<String>
+}
+
+
+
+// Function for easily running from filename.
+pub fn run(filename: &str, options: &Options) {
     logger::info(format!("Commencing print on file `{}`.", data::escapify(filename.to_string())));
 
+    let mut data = load(filename, &options.machine);
+    if let Some(export) = &options.export {
+        data.export = export.clone();
+    }
+    let threads = options.threads;
+
+    // Trace equations into paths.
+    logger::debug(format!("Tracing equations on {} threads.", renderer::thread_count(threads)));
+    let mut paths_res = paths::extract(&data, renderer::thread_count(threads));
+    if !paths_res.success {
+        logger::critical("Tracing failed. Error provided:");
+        println!("\n{}", paths_res.exception);
+        exit(1);
+    }
+
+    // Fill closed paths with hatching.
+    if data.hatches.iter().any(|hatch| hatch.spacing > 0.0) {
+        logger::debug(format!("Hatching paths."));
+        hatch::fill(&data, &mut paths_res.paths);
+    }
+
+    // Offset closed paths for the kerf.
+    if layers::groups(&data).iter().any(|group| group.layer.kerf.width > 0.0) {
+        logger::debug(format!("Offsetting paths for the kerf."));
+        let removed = offset::compensate(&data, &mut paths_res.paths);
+        if removed > 0 {
+            logger::warning(format!("{} paths were narrower than the kerf and were removed.", removed));
+        }
+    }
+
+    // Order paths to cut.
+    if data.ordering != data::Ordering::Script {
+        logger::debug(format!("Ordering {} paths.", paths_res.paths.paths.len()));
+        let report = ordering::optimise(&mut paths_res.paths, gcode::origin(&data), data.ordering);
+        logger::info(format!("Travel distance {}{} before ordering, {}{} after.", exporter::number(report.before, 2), data.units.name(), exporter::number(report.after, 2), data.units.name()));
+    }
+
+    // Export paths.
+    logger::debug(format!("Exporting to `{}`.", data::escapify(data.export.clone())));
+    let renderer_res = exporter::export(data.clone(), &paths_res.paths, threads);
+    if !renderer_res.success {
+        logger::critical("Exporting failed. Error provided:");
+        println!("\n{}", renderer_res.exception);
+        exit(1);
+    }
+
+    // Print export file.
+    if data.print_now {
+        logger::debug(format!("Printing image."));
+        let printer_res = printer::print(renderer_res.export_filename);
+        if !printer_res.success {
+            println!("{}", printer_res.exception);
+            exit(1);
+        }
+    } else {
+        logger::warning(format!("Printer disabled. Skipping."));
+    }
+    logger::success("All jobs finished.");
+}
+
+
+
+// Read, lex, parse and interpret a script file, exiting if any step fails.
+// The job is checked against the machine profile called `machine` if given, or else the script's `#machine`.
+pub fn load(filename: &str, machine: &Option<String>) -> interpreter::InterpreterData {
     // Read script file.
     logger::debug(format!("Reading file `{}`.", data::escapify(filename.to_string())));
     let script = read(filename);
@@ -37,9 +122,25 @@ pub fn run(filename: &str) {
         exit(1);
     }
 
+    // Read machine profile.
+    let machine = match machine {
+        Some(name) => {
+            logger::debug(format!("Reading machine profile `{}`.", data::escapify(name.clone())));
+            match machine::load(name, filename) {
+                Ok(machine) => Some(machine),
+                Err(e)      => {
+                    logger::critical("Reading machine profile failed. Error provided:");
+                    println!("\n{}", e);
+                    exit(1);
+                }
+            }
+        },
+        None       => None
+    };
+
     // Interpret node list.
     logger::debug(format!("Interpreting node tree."));
-    let interpreter_res = interpreter::interpret(parser_res.nodes);
+    let interpreter_res = interpreter::interpret(parser_res.nodes, machine);
     if !interpreter_res.success {
         logger::critical("Interpreting failed. Error provided:");
         for exception in interpreter_res.exceptions {
@@ -48,27 +149,7 @@ pub fn run(filename: &str) {
         exit(1);
     }
 
-    // Render interpreter data.
-    logger::debug(format!("Rendering data."));
-    let renderer_res = renderer::render(interpreter_res.data.clone());
-    if !renderer_res.success {
-        logger::critical("Rendering failed. Error provided:");
-        println!("\n{}", renderer_res.exception);
-        exit(1);
-    }
-
-    // Print export file.
-    if interpreter_res.data.print_now {
-        logger::debug(format!("Printing image."));
-        let printer_res = printer::print(renderer_res.export_filename);
-        if !printer_res.success {
-            println!("{}", printer_res.exception);
-            exit(1);
-        }
-    } else {
-        logger::warning(format!("Printer disabled. Skipping."));
-    }
-    logger::success("All jobs finished.");
+    return interpreter_res.data;
 }
 
 
@@ -85,3 +166,28 @@ fn read(filename: &str) -> String {
 
     return contents;
 }
+
+
+
+// Lex, parse and interpret a script held in memory, for tests. Panics with the errors if any step fails.
+#[cfg(test)]
+pub fn interpret_text(script : &str) -> interpreter::InterpreterData {
+    let lexer_res = lexer::lex("test.lrn".to_string(), script.to_string());
+    assert!(lexer_res.success, "{}", lexer_res.exception);
+    let parser_res = parser::parse(lexer_res.tokens);
+    assert!(parser_res.success, "{}", parser_res.exception);
+    let interpreter_res = interpreter::interpret(parser_res.nodes, None);
+    assert!(interpreter_res.success, "{}", interpreter_res.exceptions.iter().map(|e| e.to_string()).collect::<Vec<String>>().join("\n"));
+    return interpreter_res.data;
+}
+
+
+
+// Interpret a script held in memory and trace its paths on `threads` threads, for tests. Panics if any step fails.
+#[cfg(test)]
+pub fn trace_text(script : &str, threads : usize) -> (interpreter::InterpreterData, paths::Paths) {
+    let data = interpret_text(script);
+    let res  = paths::extract(&data, threads);
+    assert!(res.success, "{}", res.exception);
+    return (data, res.paths);
+}
@@ -0,0 +1,244 @@
+use super::data;
+use super::compiler;
+
+
+
+// Roots of an equation in a column, and the spans of the column where it holds at every sample, like the line of `x = 3`.
+pub struct Solution {
+    pub roots : data::MultipleValues,
+    pub spans : Vec<[f64; 2]>
+}
+
+
+
+// Function for numerically finding every `y` in a range where `left(x, y) - right(x, y) = 0`.
+// The program must be compiled from an equation that is not explicit, so it evaluates `left - right`.
+// A run of samples which are exactly zero is one root if it is a single sample, and a span otherwise.
+pub fn solve(program : &compiler::Program, x : f64, bottom : f64, top : f64, samples : i32, stack : &mut Vec<f64>) -> Solution {
+    let samples = samples.max(1);
+    let step    = (top - bottom) / samples as f64;
+    let mut roots : Vec<f64>      = vec![];
+    let mut spans : Vec<[f64; 2]> = vec![];
+
+    // Sample the difference at evenly spaced y values and look for sign changes on each branch.
+    for branch in 0..program.branches() {
+        let mut prev_y = bottom;
+        let mut fa     = program.evaluate(x, bottom, branch, stack);
+        let mut run    = if fa == 0.0 { Some(bottom) } else { None };
+        for i in 1..(samples + 1) {
+            let y  = bottom + step * i as f64;
+            let fb = program.evaluate(x, y, branch, stack);
+            if fb == 0.0 {
+                run = run.or(Some(y));
+            } else {
+                if let Some(start) = run.take() {
+                    end_run(start, prev_y, &mut roots, &mut spans);
+                } else if fa * fb < 0.0 {
+                    let mut f = |y : f64| program.evaluate(x, y, branch, stack);
+                    match find_root(&mut f, prev_y, y, fa, fb) {
+                        Some(root) => roots.push(root),
+                        None       => ()
+                    }
+                }
+            }
+            prev_y = y;
+            fa     = fb;
+        }
+        if let Some(start) = run {
+            end_run(start, prev_y, &mut roots, &mut spans);
+        }
+    }
+
+    // Merge roots and spans found by several branches at the same point.
+    roots.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    roots.dedup_by(|a, b| (*a - *b).abs() <= data::SOLVER_TOLERANCE);
+    spans.sort_by(|a, b| a[0].partial_cmp(&b[0]).unwrap());
+    spans.dedup();
+
+    return Solution {
+        roots : data::MultipleValues {
+            branches : vec![data::Branch::new(); roots.len()],
+            values   : roots
+        },
+        spans : spans
+    };
+}
+
+
+
+// Record a run of samples which are exactly zero, from `start` to `end`.
+fn end_run(start : f64, end : f64, roots : &mut Vec<f64>, spans : &mut Vec<[f64; 2]>) {
+    if start == end {
+        roots.push(start);
+    } else {
+        spans.push([start, end]);
+    }
+}
+
+
+
+// Find the root of `f` between `a` and `b`, where `f(a)` and `f(b)` have different signs.
+// Sign changes through a pole are not roots, and are rejected.
+pub fn find_root<F : FnMut(f64) -> f64>(f : &mut F, a : f64, b : f64, fa : f64, fb : f64) -> Option<f64> {
+    let mut f = |t : f64| -> Option<f64> {
+        let value = f(t);
+        if value.is_nan() {
+            return None;
+        }
+        return Some(value);
+    };
+    let root = brent(&mut f, a, b, fa, fb)?;
+    return match f(root) {
+        Some(value) if value.abs() <= fa.abs().min(fb.abs()) => Some(root),
+        _                                                    => None
+    };
+}
+
+
+
+// Brent's method for refining a bracketed root, using inverse quadratic and secant steps with bisection as a fallback.
+fn brent<F : FnMut(f64) -> Option<f64>>(f : &mut F, mut a : f64, mut b : f64, mut fa : f64, mut fb : f64) -> Option<f64> {
+    let mut c  = b;
+    let mut fc = fb;
+    let mut d  = b - a;
+    let mut e  = d;
+    for _i in 0..data::SOLVER_MAX_ITERATIONS {
+        if (fb > 0.0 && fc > 0.0) || (fb < 0.0 && fc < 0.0) {
+            c  = a;
+            fc = fa;
+            d  = b - a;
+            e  = d;
+        }
+        if fc.abs() < fb.abs() {
+            a  = b;
+            b  = c;
+            c  = a;
+            fa = fb;
+            fb = fc;
+            fc = fa;
+        }
+        let tol = 2.0 * f64::EPSILON * b.abs() + 0.5 * data::SOLVER_TOLERANCE;
+        let m   = 0.5 * (c - b);
+        if m.abs() <= tol || fb == 0.0 {
+            return Some(b);
+        }
+        if e.abs() >= tol && fa.abs() > fb.abs() {
+            let s = fb / fa;
+            let mut p;
+            let mut q;
+            if a == c {
+                p = 2.0 * m * s;
+                q = 1.0 - s;
+            } else {
+                let r = fb / fc;
+                q = fa / fc;
+                p = s * (2.0 * m * q * (q - r) - (b - a) * (r - 1.0));
+                q = (q - 1.0) * (r - 1.0) * (s - 1.0);
+            }
+            if p > 0.0 {
+                q = -q;
+            }
+            p = p.abs();
+            if 2.0 * p < (3.0 * m * q - (tol * q).abs()).min((e * q).abs()) {
+                e = d;
+                d = p / q;
+            } else {
+                d = m;
+                e = d;
+            }
+        } else {
+            d = m;
+            e = d;
+        }
+        a  = b;
+        fa = fb;
+        if d.abs() > tol {
+            b += d;
+        } else {
+            b += tol.copysign(m);
+        }
+        fb = f(b)?;
+    }
+    return Some(b);
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::run;
+
+    // Function with a root between two values, and the root.
+    type Case = (fn(f64) -> f64, f64, f64, f64);
+
+    #[test]
+    fn brent_converges() {
+        // Roots that single precision could only find to about 1e-7.
+        let cases : [Case; 3] = [
+            (|y| y * y - 2.0,     1.0, 2.0, 2.0f64.sqrt()),
+            (|y| y.cos() - y,     0.0, 1.0, 0.739_085_133_215_160_6),
+            (|y| y * y * y - 1e6, 0.0, 500.0, 100.0)
+        ];
+        for (mut f, a, b, expected) in cases {
+            let (fa, fb) = (f(a), f(b));
+            let root     = find_root(&mut f, a, b, fa, fb).unwrap();
+            assert!((root - expected).abs() <= 1e-9, "{} is not {}", root, expected);
+        }
+    }
+
+    #[test]
+    fn pole_is_not_a_root() {
+        let mut f    = |y : f64| 1.0 / (y - 0.5);
+        let (fa, fb) = (f(0.0), f(1.0));
+        assert_eq!(find_root(&mut f, 0.0, 1.0, fa, fb), None);
+    }
+
+    #[test]
+    fn solve_finds_every_root() {
+        let data      = run::interpret_text("#frame(-10, -10, 20, 20)\ny + sin(y) = 2\nx * x + y * y = 2\n");
+        let mut stack = vec![];
+
+        let program = compiler::compile(&data.equations[0]).program;
+        let res     = solve(&program, 0.0, -10.0, 10.0, data::SOLVER_MAX_SAMPLES, &mut stack).roots;
+        assert_eq!(res.values.len(), 1);
+        assert!((res.values[0] + res.values[0].sin() - 2.0).abs() <= 1e-9);
+
+        let program = compiler::compile(&data.equations[1]).program;
+        let res     = solve(&program, 1.0, -10.0, 10.0, data::SOLVER_MAX_SAMPLES, &mut stack).roots;
+        assert_eq!(res.values.len(), 2);
+        assert!((res.values[0] + 1.0).abs() <= 1e-9 && (res.values[1] - 1.0).abs() <= 1e-9, "{:?}", res.values);
+    }
+
+    #[test]
+    fn num_root_converges() {
+        for (exp, value) in [(2.0, 2.0), (3.0, 12345.678), (2.0, 1e12), (5.0, 0.001)] {
+            let root = data::MultipleValues::num_root(exp, value);
+            assert!((root.powf(exp) - value).abs() <= value * 1e-9, "{}root({}) gave {}", exp, value, root);
+        }
+    }
+
+    // Samples which are exactly zero used to each be a root, so `x = 3` gave a root at every sample of its column.
+    #[test]
+    fn zero_samples() {
+        let data      = run::interpret_text("#frame(-10, -10, 20, 20)\nx = 3\nx * x + y * y = 9\n");
+        let mut stack = vec![];
+
+        let program = compiler::compile(&data.equations[0]).program;
+        let res     = solve(&program, 3.0, -10.0, 10.0, 20, &mut stack);
+        assert!(res.roots.values.is_empty(), "{:?}", res.roots.values);
+        assert_eq!(res.spans, vec![[-10.0, 10.0]]);
+        assert!(solve(&program, 2.0, -10.0, 10.0, 20, &mut stack).spans.is_empty());
+
+        // A single sample on a root is one root.
+        let program = compiler::compile(&data.equations[1]).program;
+        let res     = solve(&program, 3.0, -10.0, 10.0, 20, &mut stack);
+        assert_eq!(res.roots.values, vec![0.0]);
+        assert!(res.spans.is_empty());
+
+        let (_data, paths) = run::trace_text("#frame(-10, -10, 20, 20)\nx = 3\n", 1);
+        assert_eq!(paths.paths.len(), 1);
+        let points = paths.paths[0].points.iter().map(|point| (point.x, point.y)).collect::<Vec<(f64, f64)>>();
+        assert_eq!(points, vec![(3.0, -10.0), (3.0, 10.0)]);
+    }
+}
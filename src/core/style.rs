@@ -0,0 +1,144 @@
+use super::data;
+use super::interpreter;
+use super::layers;
+use super::fitting::{add, sub, scale, distance};
+
+
+
+// Get the colour an equation is drawn in, and its AutoCAD colour index.
+// Its `colour` attribute if given, and otherwise the colour of its layer.
+pub fn colour(data : &interpreter::InterpreterData, equation : usize) -> (data::Colour, i32) {
+    return match data.styles[equation].colour {
+        Some(colour) => (colour, colour.aci()),
+        None         => layers::colour(data, equation)
+    };
+}
+
+
+
+// Get the width of an equation's lines in vector exports, in units.
+// Its `width` attribute if given, and otherwise `#thickness`. Lines with no width are one pixel wide.
+pub fn width(data : &interpreter::InterpreterData, equation : usize) -> f64 {
+    let width = data.styles[equation].width.unwrap_or(data.thickness);
+    if width > 0.0 {
+        return width;
+    }
+    return data.frame().w / data.pixel_resolution().x as f64;
+}
+
+
+
+// Function for splitting a line into the dashes of a pattern of dash and gap lengths, which starts again on every line.
+// A pattern with an odd number of lengths is used twice over, so dashes and gaps swap the second time, as in SVG.
+// Lines are returned whole when the pattern is empty.
+pub fn dashes(points : &[data::Point], pattern : &[f64]) -> Vec<Vec<data::Point>> {
+    if pattern.is_empty() {
+        return vec![points.to_vec()];
+    }
+    let mut lengths = pattern.to_vec();
+    if lengths.len() % 2 == 1 {
+        lengths.extend_from_slice(pattern);
+    }
+
+    // Even places in the pattern are dashes and odd places are gaps.
+    let mut res     = vec![];
+    let mut place   = 0;
+    let mut left    = lengths[0];
+    let mut current = vec![points[0]];
+    for pair in points.windows(2) {
+        let (a, b)   = (pair[0], pair[1]);
+        let length   = distance(a, b);
+        let mut done = 0.0;
+        while length - done > left {
+            done += left;
+            let at = add(a, scale(sub(b, a), done / length));
+            if place % 2 == 0 {
+                current.push(at);
+                res.push(current);
+                current = vec![];
+            } else {
+                current = vec![at];
+            }
+            place = (place + 1) % lengths.len();
+            left  = lengths[place];
+        }
+        left -= length - done;
+        if place % 2 == 0 {
+            current.push(b);
+        }
+    }
+    if current.len() > 1 {
+        res.push(current);
+    }
+    return res;
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::run;
+    use super::super::lexer;
+    use super::super::parser;
+
+    // Check whether a script is turned down while parsing or interpreting.
+    fn rejected(script : &str) -> bool {
+        let lexer_res  = lexer::lex("test.lrn".to_string(), script.to_string());
+        let parser_res = parser::parse(lexer_res.tokens);
+        return ! parser_res.success || ! interpreter::interpret(parser_res.nodes, None).success;
+    }
+
+    // Get the points of some dashes as pairs, to compare them exactly.
+    fn pairs(dashes : Vec<Vec<data::Point>>) -> Vec<Vec<(f64, f64)>> {
+        return dashes.into_iter().map(|dash| dash.into_iter().map(|point| (point.x, point.y)).collect()).collect();
+    }
+
+    #[test]
+    fn attributes() {
+        let data = run::interpret_text("y = x [colour(\"#f80\"), width(0.5), dash(2, 1)]\ny = 2 [dash(3), colour(\"red\")]\ny = 3\n");
+        assert_eq!(data.styles[0], data::Style {
+            colour : Some(data::Colour {r : 1.0, g : 136.0 / 255.0, b : 0.0, a : 1.0}),
+            width  : Some(0.5),
+            dash   : vec![2.0, 1.0]
+        });
+        assert_eq!(data.styles[1], data::Style {
+            colour : Some(data::Colour {r : 1.0, g : 0.0, b : 0.0, a : 1.0}),
+            width  : None,
+            dash   : vec![3.0]
+        });
+        assert_eq!(data.styles[2], data::Style {
+            colour : None,
+            width  : None,
+            dash   : vec![]
+        });
+
+        assert!(! rejected("y = x [colour(\"#ff8800\")]\n"));
+        for script in ["y = x [width(1), width(2)]\n", "y = x [dash()]\n", "y = x [dash(1, 0)]\n", "y = x [width(-1)]\n", "y = x [colour(\"#ff88\")]\n", "y = x [colour(\"mauve\")]\n"] {
+            assert!(rejected(script), "{}", script);
+        }
+    }
+
+    #[test]
+    fn dash_lengths() {
+        let line = [data::Point {x : 0.0, y : 0.0}, data::Point {x : 10.0, y : 0.0}];
+        assert_eq!(pairs(dashes(&line, &[])), vec![vec![(0.0, 0.0), (10.0, 0.0)]]);
+        assert_eq!(pairs(dashes(&line, &[2.0, 1.0])), vec![
+            vec![(0.0, 0.0), (2.0, 0.0)],
+            vec![(3.0, 0.0), (5.0, 0.0)],
+            vec![(6.0, 0.0), (8.0, 0.0)],
+            vec![(9.0, 0.0), (10.0, 0.0)]
+        ]);
+        // An odd pattern is used twice over, so a single length gives equal dashes and gaps.
+        assert_eq!(pairs(dashes(&line, &[3.0])), vec![
+            vec![(0.0, 0.0), (3.0, 0.0)],
+            vec![(6.0, 0.0), (9.0, 0.0)]
+        ]);
+        // Dashes carry on around corners.
+        let corner = [data::Point {x : 0.0, y : 0.0}, data::Point {x : 2.0, y : 0.0}, data::Point {x : 2.0, y : 4.0}];
+        assert_eq!(pairs(dashes(&corner, &[3.0, 1.0])), vec![
+            vec![(0.0, 0.0), (2.0, 0.0), (2.0, 1.0)],
+            vec![(2.0, 2.0), (2.0, 4.0)]
+        ]);
+    }
+}
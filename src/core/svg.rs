@@ -0,0 +1,162 @@
+use super::data;
+use super::interpreter;
+use super::paths;
+use super::renderer;
+use super::exporter;
+use super::fitting;
+use super::layers;
+use super::style;
+
+
+
+// Function for writing paths as an SVG document.
+// Positions are in `#units` units on the bed, so the drawing keeps its physical size when opened elsewhere.
+// Each equation's paths are grouped into a `<g>`, and every path is titled with its source expression.
+// Equations on a layer are grouped again into a `<g>` for the layer, titled with its name.
+// Each equation is stroked with the colour, width and dashes of its style.
+pub fn export(data : &interpreter::InterpreterData, paths : &paths::Paths) -> renderer::RendererResult {
+    return exporter::write(&data.export, document(data, paths));
+}
+
+
+
+// Build the document written by `export`.
+pub fn document(data : &interpreter::InterpreterData, paths : &paths::Paths) -> String {
+    let frame  = paths.frame;
+
+    let mut res = String::new();
+    res += "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n";
+    res += &format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}{}\" height=\"{}{}\" viewBox=\"0 0 {} {}\">\n",
+        exporter::number(frame.w, 4), data.units.name(), exporter::number(frame.h, 4), data.units.name(),
+        exporter::number(frame.w, 4), exporter::number(frame.h, 4)
+    );
+    for group in layers::groups(data) {
+        let indent = match group.index {
+            Some(index) => {
+                res += &format!("  <g id=\"layer-{}\">\n", index);
+                res += &format!("    <title>{}</title>\n", exporter::escape_xml(&group.layer.name));
+                "  "
+            },
+            None        => ""
+        };
+        for index in group.equations.iter() {
+            res += &equation_group(data, paths, *index, indent);
+        }
+        if group.index.is_some() {
+            res += "  </g>\n";
+        }
+    }
+    res += "</svg>\n";
+
+    return res;
+}
+
+
+
+// Build the `<g>` holding an equation's paths, indented by `indent`.
+fn equation_group(data : &interpreter::InterpreterData, paths : &paths::Paths, index : usize, indent : &str) -> String {
+    let dash    = &data.styles[index].dash;
+    let dashes  = if dash.is_empty() {
+        "".to_string()
+    } else {
+        format!(" stroke-dasharray=\"{}\"", dash.iter().map(|length| exporter::number(*length, 4)).collect::<Vec<String>>().join(" "))
+    };
+    let mut res = format!(
+        "{}  <g id=\"equation-{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"{}\"{} stroke-linecap=\"round\" stroke-linejoin=\"round\">\n",
+        indent, index, stroke_colour(style::colour(data, index).0), exporter::number(style::width(data, index), 4), dashes
+    );
+    for path in paths.paths.iter().filter(|path| path.equation == index) {
+        res += &format!("{}    <path d=\"{}\">\n", indent, path_data(path, &paths.frame, fitting::stage_tolerance(data)));
+        res += &format!("{}      <title>{}</title>\n", indent, exporter::escape_xml(&paths.equations[index]));
+        res += &format!("{}    </path>\n", indent);
+    }
+    res += &format!("{}  </g>\n", indent);
+    return res;
+}
+
+
+
+// Build the `d` attribute of a path, fitting curves to it within `tolerance`.
+// SVG y values run top to bottom, so the frame is flipped.
+fn path_data(path : &paths::Path, frame : &paths::Frame, tolerance : f64) -> String {
+    let position = |point : data::Point| format!(
+        "{} {}",
+        exporter::number(point.x - frame.x, 4),
+        exporter::number(frame.y + frame.h - point.y, 4)
+    );
+    let mut res  = vec![format!("M{}", position(path.points[0]))];
+    for segment in fitting::fit_cubics(&path.points, tolerance) {
+        res.push(match segment {
+            fitting::Segment::Cubic {control1, control2, to} => format!("C{} {} {}", position(control1), position(control2), position(to)),
+            // Arcs are only fitted for G-code.
+            fitting::Segment::Line  {to}                     |
+            fitting::Segment::Arc   {to, ..}                 => format!("L{}", position(to))
+        });
+    }
+    // A closed path ends where it starts, and `Z` joins the ends smoothly.
+    if path.closed {
+        res.push("Z".to_string());
+    }
+    return res.join(" ");
+}
+
+
+
+// Format a colour for a stroke, by name for black so drawings without layers are unchanged.
+fn stroke_colour(colour : data::Colour) -> String {
+    let channel = |value : f32| (value * 255.0).round() as u8;
+    let res     = format!("#{:02x}{:02x}{:02x}", channel(colour.r), channel(colour.g), channel(colour.b));
+    return if res == "#000000" { "black".to_string() } else { res };
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::run;
+
+    // Read the value of an attribute from the first element holding it.
+    fn attribute(text : &str, name : &str) -> String {
+        let start = text.find(&format!(" {}=\"", name)).unwrap() + name.len() + 3;
+        return text[start..].split('"').next().unwrap().to_string();
+    }
+
+    // The circle has its centre at (1, 2) on the bed, which is (6, 3) in the image as y is flipped, and a radius of 3.
+    // Without a tolerance it is drawn with lines, and with one it is fitted with cubics, which stay within the tolerance of the circle.
+    #[test]
+    fn circle() {
+        for headers in ["", "#tolerance(0.02)\n#resolution(400, 400)\n"] {
+            let (data, paths) = run::trace_text(&format!("{}#frame(-5, -5, 10, 10)\nx * x + y * y - 2 * x - 4 * y - 4 = 0\n", headers), 1);
+            let text          = document(&data, &paths);
+            assert_eq!(attribute(&text, "width"), "10mm");
+            assert_eq!(attribute(&text, "height"), "10mm");
+            assert_eq!(attribute(&text, "viewBox"), "0 0 10 10");
+
+            assert_eq!(paths.paths.len(), 1);
+            let d        = attribute(&text, "d");
+            let words    = d.split(' ').collect::<Vec<&str>>();
+            let number   = |word : &str| word.trim_start_matches(['M', 'C', 'L']).parse::<f64>().unwrap();
+            let off      = |x : f64, y : f64| ((x - 6.0).hypot(y - 3.0) - 3.0).abs();
+            let mut last = (number(words[0]), number(words[1]));
+            let mut at   = 2;
+            assert!(words[0].starts_with('M') && words[words.len() - 1] == "Z", "{}", d);
+            assert_eq!(d.contains('C'), ! headers.is_empty(), "{}", d);
+            while at < words.len() - 1 {
+                let count  = if words[at].starts_with('C') { 6 } else { 2 };
+                let values = words[at..(at + count)].iter().map(|word| number(word)).collect::<Vec<f64>>();
+                let end    = (values[count - 2], values[count - 1]);
+                assert!(off(end.0, end.1) <= 1e-3, "{:?} is off the circle.", end);
+                // Cubics must also follow the circle between their ends.
+                if count == 6 {
+                    let middle = ((last.0 + 3.0 * values[0] + 3.0 * values[2] + end.0) / 8.0, (last.1 + 3.0 * values[1] + 3.0 * values[3] + end.1) / 8.0);
+                    assert!(off(middle.0, middle.1) <= 0.02, "{:?} is off the circle.", middle);
+                }
+                last = end;
+                at  += count;
+            }
+            assert!(at > 6, "{}", d);
+        }
+    }
+}
@@ -6,29 +6,32 @@ use super::data;
 
 // Different token types
 
-pub const TK_NULL     : &'static str = "null";
+pub const TK_NULL      : &'static str = "null";
 
-pub const TK_VARIABLE : &'static str = "variable";
-pub const TK_NUMBER   : &'static str = "number";
-pub const TK_STRING   : &'static str = "string";
+pub const TK_VARIABLE  : &'static str = "variable";
+pub const TK_NUMBER    : &'static str = "number";
+pub const TK_STRING    : &'static str = "string";
 
-pub const TK_ADD      : &'static str = "+";
-pub const TK_SUBTRACT : &'static str = "-";
-pub const TK_MULTIPLY : &'static str = "*";
-pub const TK_DIVIDE   : &'static str = "/";
+pub const TK_ADD       : &'static str = "+";
+pub const TK_SUBTRACT  : &'static str = "-";
+pub const TK_MULTIPLY  : &'static str = "*";
+pub const TK_DIVIDE    : &'static str = "/";
 
-pub const TK_EQUALS   : &'static str = "=";
+pub const TK_EQUALS    : &'static str = "=";
 
-pub const TK_LPAREN   : &'static str = "lparen";
-pub const TK_RPAREN   : &'static str = "rparen";
+pub const TK_LPAREN    : &'static str = "lparen";
+pub const TK_RPAREN    : &'static str = "rparen";
+pub const TK_LBRACKET  : &'static str = "lbracket";
+pub const TK_RBRACKET  : &'static str = "rbracket";
 
-pub const TK_HEADER   : &'static str = "#";
-pub const TK_HEADFUNC : &'static str = "headfunc";
-pub const TK_FUNCTION : &'static str = "function";
-pub const TK_COMMA    : &'static str = ",";
+pub const TK_HEADER    : &'static str = "#";
+pub const TK_HEADFUNC  : &'static str = "headfunc";
+pub const TK_FUNCTION  : &'static str = "function";
+pub const TK_ATTRIBUTE : &'static str = "attribute";
+pub const TK_COMMA     : &'static str = ",";
 
-pub const TK_EOL      : &'static str = "eol";
-pub const TK_EOF      : &'static str = "eof";
+pub const TK_EOL       : &'static str = "eol";
+pub const TK_EOF       : &'static str = "eof";
 
 
 
@@ -43,9 +46,9 @@ pub struct Token {
 impl fmt::Display for Token {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         if self.value.len() >= 1 {
-            write!(f, "{}", format!("<{}: {}>", self.name, self.value))
+            write!(f, "<{}: {}>", self.name, self.value)
         } else {
-            write!(f, "{}", format!("<{}>", self.name))
+            write!(f, "<{}>", self.name)
         }
     }
 }
@@ -0,0 +1,677 @@
+use std::collections::HashMap;
+use std::thread;
+
+use super::logger;
+use super::data;
+use super::nodes;
+use super::exceptions;
+use super::interpreter;
+use super::compiler;
+use super::solver;
+
+
+
+// Success/Failure identification class.
+#[derive(Clone, Debug)]
+pub struct TracerResult {
+    pub success   : bool,
+    pub curves    : Vec<Vec<data::Point>>,
+    pub exception : exceptions::RendererException
+}
+
+
+
+// Curve that is still being extended, and the branch it follows.
+struct OpenCurve {
+    id       : usize,
+    branch   : data::Branch,
+    extended : bool
+}
+
+
+
+// Points joining the ends of two curves where an equation folds back on itself.
+// `a_end` and `b_end` are true for the last point of a curve, and false for the first.
+struct Join {
+    a      : usize,
+    a_end  : bool,
+    b      : usize,
+    b_end  : bool,
+    bridge : Vec<data::Point>
+}
+
+
+
+// End of a curve, as the curve id and whether it is the last point rather than the first.
+type CurveEnd = (usize, bool);
+
+
+
+// Tracer class
+struct Tracer<'a> {
+    program : &'a compiler::Program,
+    data    : &'a interpreter::InterpreterData,
+    pixel   : data::Point,
+    stack   : Vec<f64>,
+    bounds  : Vec<data::Interval>
+}
+impl<'a> Tracer<'a> {
+    // Add points between two neighbouring points on a curve until every segment is shorter than a pixel.
+    // The points are added in order from `a` to `b`, without `a` and `b` themselves.
+    fn refine(&mut self, a : data::Point, b : data::Point, branch : Option<usize>, depth : i32, points : &mut Vec<data::Point>) {
+        if depth <= 0 || self.pixel_length(a, b) <= 1.0 || ! self.visible(a, b) {
+            return;
+        }
+        let middle = match self.middle(a, b, branch) {
+            Some(middle) => middle,
+            None         => return
+        };
+        self.refine(a, middle, branch, depth - 1, points);
+        points.push(middle);
+        self.refine(middle, b, branch, depth - 1, points);
+    }
+
+    // Find a point on the curve halfway between two points.
+    // Explicit equations are evaluated halfway along x, others are searched for along the line through
+    // the middle of the segment at right angles to it.
+    fn middle(&mut self, a : data::Point, b : data::Point, branch : Option<usize>) -> Option<data::Point> {
+        let middle = data::Point {
+            x : (a.x + b.x) / 2.0,
+            y : (a.y + b.y) / 2.0
+        };
+        match branch {
+            Some(branch) => {
+                let y = self.program.evaluate(middle.x, 0.0, branch, &mut self.stack);
+                if ! y.is_finite() {
+                    return None;
+                }
+                return Some(data::Point {x : middle.x, y : y});
+            },
+            None         => ()
+        };
+
+        // Search direction, scaled so the search is square in pixels.
+        let dx     = (b.x - a.x) / self.pixel.x;
+        let dy     = (b.y - a.y) / self.pixel.y;
+        let length = (dx * dx + dy * dy).sqrt();
+        let normal = data::Point {
+            x : -dy / length * self.pixel.x,
+            y : dx / length * self.pixel.y
+        };
+        let reach  = length / 2.0;
+        let steps  = data::TRACER_SEARCH_STEPS;
+        let mut closest : Option<f64> = None;
+        for branch in 0..self.program.branches() {
+            let program = self.program;
+            let stack   = &mut self.stack;
+            let mut f   = |t : f64| program.evaluate(middle.x + normal.x * t, middle.y + normal.y * t, branch, stack);
+            let mut ta  = -reach;
+            let mut fa  = f(ta);
+            for i in 1..(2 * steps + 1) {
+                let tb   = reach * (i - steps) as f64 / steps as f64;
+                let fb   = f(tb);
+                let root = if fa == 0.0 {
+                    Some(ta)
+                } else if fa * fb < 0.0 {
+                    solver::find_root(&mut f, ta, tb, fa, fb)
+                } else {
+                    None
+                };
+                match (root, closest) {
+                    (Some(t), Some(c)) if c.abs() <= t.abs() => (),
+                    (Some(t), _)                             => closest = Some(t),
+                    _                                        => ()
+                }
+                ta = tb;
+                fa = fb;
+            }
+        }
+        return closest.map(|t| data::Point {
+            x : middle.x + normal.x * t,
+            y : middle.y + normal.y * t
+        });
+    }
+
+    // Length of a segment in pixels.
+    fn pixel_length(&self, a : data::Point, b : data::Point) -> f64 {
+        let dx = (b.x - a.x) / self.pixel.x;
+        let dy = (b.y - a.y) / self.pixel.y;
+        return (dx * dx + dy * dy).sqrt();
+    }
+
+    // Check if the curve between two points might pass through the frame, so is worth refining.
+    fn visible(&mut self, a : data::Point, b : data::Point) -> bool {
+        let bottom = self.data.position.y as f64;
+        let top    = bottom + self.data.size.y as f64;
+        if self.program.explicit {
+            let x = data::Interval::new(a.x.min(b.x), a.x.max(b.x));
+            return self.program.crosses(x, data::Interval::new(bottom, top), &mut self.bounds);
+        }
+        return a.y.max(b.y) >= bottom && a.y.min(b.y) <= top;
+    }
+
+    // Find points joining two points solved numerically, in order from `a` to `b` and without them.
+    // The curve is searched for halfway between the points, and between those, until every gap is under a pixel.
+    // Returns none if a gap can not be closed, as the points are then on separate curves or either side of a break.
+    // This tells a steep part of a curve, where samples are far apart, from a jump to another curve.
+    fn connect(&mut self, a : data::Point, b : data::Point) -> Option<Vec<data::Point>> {
+        if find_break(self.program, a, b, None, &mut self.bounds) != compiler::Break::None {
+            return None;
+        }
+        let mut points = vec![];
+        if ! self.connect_into(a, b, data::TRACER_MAX_DEPTH, &mut points) {
+            return None;
+        }
+        return Some(points);
+    }
+
+    fn connect_into(&mut self, a : data::Point, b : data::Point, depth : i32, points : &mut Vec<data::Point>) -> bool {
+        if self.pixel_length(a, b) <= 1.0 || ! self.visible(a, b) {
+            return true;
+        }
+        // Gaps left at the deepest level may still be a little over a pixel along a curve.
+        if depth <= 0 {
+            return self.pixel_length(a, b) <= 2.0;
+        }
+        let middle = match self.middle(a, b, None) {
+            Some(middle) => middle,
+            None         => return false
+        };
+        if ! self.connect_into(a, middle, depth - 1, points) {
+            return false;
+        }
+        points.push(middle);
+        return self.connect_into(middle, b, depth - 1, points);
+    }
+}
+
+
+
+// Function for tracing every branch of an equation into separate continuous curves, sampled at each column edge.
+// Curves are broken wherever the equation is discontinuous between two samples, and points are added between
+// samples until every segment is shorter than a pixel. Columns are sampled on `threads` threads.
+pub fn trace(equation : &nodes::Node, data : &interpreter::InterpreterData, threads : usize) -> TracerResult {
+    let compiled = compiler::compile(equation);
+    if ! compiled.success {
+        return TracerResult {
+            success   : false,
+            curves    : vec![],
+            exception : compiled.exception
+        };
+    }
+    let program    = compiled.program;
+    let resolution = data.pixel_resolution();
+    match check_division_by_zero(&program, equation, data) {
+        Some(exception) => return TracerResult {
+            success   : false,
+            curves    : vec![],
+            exception : exception
+        },
+        None            => ()
+    };
+    let columns    = sample(&program, data, threads);
+    let mut tracer = Tracer {
+        program : &program,
+        data    : data,
+        pixel   : data::Point {
+            x : data.size.x as f64 / resolution.x as f64,
+            y : data.size.y as f64 / resolution.y as f64
+        },
+        stack   : vec![],
+        bounds  : vec![]
+    };
+
+    let mut open   : Vec<OpenCurve>       = vec![];
+    let mut curves : Vec<Vec<data::Point>> = vec![];
+    let mut joins  : Vec<Join>            = vec![];
+    let mut starts : Vec<usize>           = vec![];
+    let mut ends   : Vec<usize>           = vec![];
+    for (column, solution) in columns.into_iter().enumerate() {
+        let x     = column_to_x(column as i32, data);
+        let value = solution.roots;
+
+        // Spans of the column where the equation holds all along are vertical lines of their own.
+        for span in solution.spans {
+            curves.push(vec![data::Point {x : x, y : span[0]}, data::Point {x : x, y : span[1]}]);
+        }
+
+        // Extend the closest curve on the same branch, or start a new one.
+        for curve in open.iter_mut() {
+            curve.extended = false;
+        }
+        let mut started = vec![];
+        for (index, (y, branch)) in value.values.into_iter().zip(value.branches).enumerate() {
+            if ! y.is_finite() {
+                continue;
+            }
+            // Values solved numerically are not tied to one branch.
+            let branch_index   = if program.explicit { Some(index) } else { None };
+            let point          = data::Point {x : x, y : y};
+            let mut candidates = vec![];
+            for (index, curve) in open.iter().enumerate() {
+                if curve.extended || curve.branch != branch {
+                    continue;
+                }
+                let last = curves[curve.id][curves[curve.id].len() - 1];
+                if find_break(&program, last, point, branch_index, &mut tracer.bounds) != compiler::Break::None {
+                    continue;
+                }
+                candidates.push((index, (last.y - y).abs()));
+            }
+
+            // A branch is followed to its closest curve. Without a branch, the closest curve the point is connected to is extended.
+            candidates.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+            let mut closest = None;
+            for (index, _distance) in candidates {
+                let id   = open[index].id;
+                let last = curves[id][curves[id].len() - 1];
+                if program.explicit {
+                    let mut points = vec![];
+                    tracer.refine(last, point, branch_index, data::TRACER_MAX_DEPTH, &mut points);
+                    closest = Some((index, points));
+                    break;
+                }
+                if let Some(points) = tracer.connect(last, point) {
+                    closest = Some((index, points));
+                    break;
+                }
+            }
+            match closest {
+                Some((index, points)) => {
+                    let id = open[index].id;
+                    curves[id].extend(points);
+                    curves[id].push(point);
+                    open[index].extended = true;
+                },
+                None                  => {
+                    started.push(curves.len());
+                    open.push(OpenCurve {
+                        id       : curves.len(),
+                        branch   : branch,
+                        extended : true
+                    });
+                    curves.push(vec![point]);
+                }
+            }
+        }
+
+        // Curves which were not extended have ended.
+        let mut ended = vec![];
+        let mut index = 0;
+        while index < open.len() {
+            if open[index].extended {
+                index += 1;
+            } else {
+                ended.push(open.remove(index).id);
+            }
+        }
+
+        // Neighbouring curves which start or end together may be two halves of a fold, like the sides of a circle.
+        // The halves can be a column apart when the tip lands exactly on a column edge, so ends which
+        // were not joined are kept for one more column.
+        if ! program.explicit {
+            starts.extend(started.iter());
+            ends.extend(ended.iter());
+            starts = join_folds(&mut tracer, &curves, starts, false, &mut joins);
+            ends   = join_folds(&mut tracer, &curves, ends, true, &mut joins);
+            starts.retain(|id| started.contains(id));
+            ends.retain(|id| ended.contains(id));
+        }
+    }
+
+    return TracerResult {
+        success   : true,
+        curves    : stitch(curves, joins),
+        exception : exceptions::RendererException {
+            base    : exceptions::RendererExceptionBase::NoException,
+            message : "".to_string(),
+            range   : equation.range.clone()
+        }
+    };
+}
+
+
+
+// Get the x value of a column edge.
+fn column_to_x(column : i32, data : &interpreter::InterpreterData) -> f64 {
+    return data.position.x as f64 + (data.size.x as f64 * (column as f64 / data.pixel_resolution().x as f64));
+}
+
+
+
+// Sample the values of an equation at every column edge, splitting the columns between threads.
+fn sample(program : &compiler::Program, data : &interpreter::InterpreterData, threads : usize) -> Vec<solver::Solution> {
+    // Solve a pixel past the frame, so curves reach its edges before they are clipped.
+    let resolution = data.pixel_resolution();
+    let margin     = data.size.y as f64 / resolution.y as f64;
+    let bottom     = data.position.y as f64 - margin;
+    let top        = data.position.y as f64 + data.size.y as f64 + margin;
+    let samples    = resolution.y.min(data::SOLVER_MAX_SAMPLES) + 2;
+    let columns    = (resolution.x + 1) as usize;
+    let chunk      = columns.div_ceil(threads.max(1));
+    let mut values = vec![];
+    thread::scope(|scope| {
+        let mut handles = vec![];
+        for start in (0..columns).step_by(chunk) {
+            handles.push(scope.spawn(move || {
+                let mut stack  = vec![];
+                let mut values = vec![];
+                for column in start..(start + chunk).min(columns) {
+                    let x = column_to_x(column as i32, data);
+                    values.push(if program.explicit {
+                        solver::Solution {
+                            roots : program.values(x, 0.0, &mut stack),
+                            spans : vec![]
+                        }
+                    } else {
+                        solver::solve(program, x, bottom, top, samples, &mut stack)
+                    });
+                }
+                values
+            }));
+        }
+        for handle in handles {
+            values.append(&mut handle.join().unwrap());
+        }
+    });
+    return values;
+}
+
+
+
+// Join neighbouring curves which start, or end, together and are connected through a fold.
+// Returns the curves which were not joined.
+fn join_folds(tracer : &mut Tracer, curves : &[Vec<data::Point>], mut ids : Vec<usize>, at_end : bool, joins : &mut Vec<Join>) -> Vec<usize> {
+    let point = |id : usize| if at_end { curves[id][curves[id].len() - 1] } else { curves[id][0] };
+    ids.sort_by(|a, b| point(*a).y.partial_cmp(&point(*b).y).unwrap());
+    let mut unjoined = vec![];
+    let mut index    = 0;
+    while index < ids.len() {
+        if index + 1 == ids.len() {
+            unjoined.push(ids[index]);
+            break;
+        }
+        let (a, b) = (ids[index], ids[index + 1]);
+        match tracer.connect(point(a), point(b)) {
+            Some(bridge) => {
+                joins.push(Join {
+                    a      : a,
+                    a_end  : at_end,
+                    b      : b,
+                    b_end  : at_end,
+                    bridge : bridge
+                });
+                index += 2;
+                continue;
+            },
+            None         => ()
+        }
+        unjoined.push(a);
+        index += 1;
+    }
+    return unjoined;
+}
+
+
+
+// Connect curves through their joins into the final curves, dropping any with less than one segment.
+fn stitch(curves : Vec<Vec<data::Point>>, joins : Vec<Join>) -> Vec<Vec<data::Point>> {
+    // Look up the join at each curve end, with its bridge ordered away from that end.
+    let mut links : HashMap<CurveEnd, (CurveEnd, Vec<data::Point>)> = HashMap::new();
+    for join in joins {
+        let mut reversed = join.bridge.clone();
+        reversed.reverse();
+        links.insert((join.a, join.a_end), ((join.b, join.b_end), join.bridge));
+        links.insert((join.b, join.b_end), ((join.a, join.a_end), reversed));
+    }
+
+    let mut visited = vec![false; curves.len()];
+    let mut res     = vec![];
+    for id in 0..curves.len() {
+        if visited[id] {
+            continue;
+        }
+        // Walk back to the first curve of the chain, or around a loop back to this one.
+        let mut first = (id, false);
+        loop {
+            match links.get(&first) {
+                Some(((other, other_end), _)) if *other != id => first = (*other, ! *other_end),
+                _                                             => break
+            }
+        }
+
+        // Walk forward, adding each curve in the direction it is entered.
+        let mut points  = vec![];
+        let mut current = first;
+        loop {
+            let (current_id, entered_at_end) = current;
+            visited[current_id] = true;
+            let curve = &curves[current_id];
+            if entered_at_end {
+                points.extend(curve.iter().rev());
+            } else {
+                points.extend(curve.iter());
+            }
+            match links.get(&(current_id, ! entered_at_end)) {
+                Some(((other, other_end), bridge)) => {
+                    points.extend(bridge.iter());
+                    if visited[*other] {
+                        // Close the loop.
+                        points.push(points[0]);
+                        break;
+                    }
+                    current = (*other, *other_end);
+                },
+                None                               => break
+            }
+        }
+        if points.len() >= 2 {
+            res.push(points);
+        }
+    }
+    return res;
+}
+
+
+
+// Find if the curve is discontinuous between two neighbouring points, using the bounds of the box between them.
+// Without a branch, the points are only broken apart if every branch is.
+fn find_break(program : &compiler::Program, a : data::Point, b : data::Point, branch : Option<usize>, stack : &mut Vec<data::Interval>) -> compiler::Break {
+    let x = data::Interval::new(a.x.min(b.x), a.x.max(b.x));
+    let y = data::Interval::new(a.y.min(b.y), a.y.max(b.y));
+    return match branch {
+        Some(branch) => program.find_break(x, y, branch, stack),
+        None         => {
+            let mut res = compiler::Break::None;
+            for branch in 0..program.branches() {
+                res = program.find_break(x, y, branch, stack);
+                if res == compiler::Break::None {
+                    break;
+                }
+            }
+            res
+        }
+    };
+}
+
+
+
+// Check every column of the frame for a division by zero, and report the first one found as the script asks.
+// Returns the exception to stop with, or none if there was no division by zero or it was only a warning.
+pub fn check_division_by_zero(program : &compiler::Program, equation : &nodes::Node, data : &interpreter::InterpreterData) -> Option<exceptions::RendererException> {
+    let resolution = data.pixel_resolution();
+    let y          = data::Interval::new(data.position.y as f64, (data.position.y + data.size.y) as f64);
+    let mut stack  = vec![];
+    for column in 0..resolution.x {
+        let x1 = column_to_x(column, data);
+        let x2 = column_to_x(column + 1, data);
+        for branch in 0..program.branches() {
+            if program.find_break(data::Interval::new(x1, x2), y, branch, &mut stack) != compiler::Break::DivisionByZero {
+                continue;
+            }
+            let message = format!("Division by zero in `{}` between x = {} and x = {}.", equation, x1, x2);
+            if data.division_by_zero == data::ExceptionLevel::Error {
+                return Some(exceptions::RendererException {
+                    base    : exceptions::RendererExceptionBase::DivisionByZeroException,
+                    message : message,
+                    range   : equation.range.clone()
+                });
+            }
+            logger::warning(message);
+            return None;
+        }
+    }
+    return None;
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::run;
+    use super::super::paths;
+
+    // Single precision values are 0.0625 apart near a million, much coarser than a column of these frames.
+    #[test]
+    fn columns_do_not_drift() {
+        let data  = run::interpret_text("#frame(1000000, -50, 300, 100)\n#resolution(16384, 16)\ny = x\n");
+        let width = 300.0 / 16384.0;
+        for column in 0..16385 {
+            let x = column_to_x(column, &data);
+            assert!((x - (1000000.0 + column as f64 * width)).abs() <= 1e-9, "Column {} is at {}.", column, x);
+        }
+        assert_eq!(column_to_x(16384, &data), 1000300.0);
+    }
+
+    #[test]
+    fn offset_frame_traces_exactly() {
+        let data = run::interpret_text("#frame(1000000, -50, 300, 100)\n#resolution(4096, 64)\ny = x - 1000150\n");
+        let res  = trace(&data.equations[0], &data, 1);
+        assert!(res.success, "{}", res.exception);
+        assert_eq!(res.curves.len(), 1);
+        let curve = &res.curves[0];
+        for point in curve.iter() {
+            assert!((point.y - (point.x - 1000150.0)).abs() <= 1e-6, "{:?} is off the line.", point);
+        }
+        // Curves are traced across every column, and only clipped to the frame when paths are extracted.
+        assert_eq!((curve[0].x, curve[curve.len() - 1].x), (1000000.0, 1000300.0));
+    }
+
+    // Trace the only equation of a script, checking no gap along a curve is over two pixels of the frame.
+    fn trace_connected(script : &str) -> Vec<Vec<data::Point>> {
+        let data  = run::interpret_text(script);
+        let res   = trace(&data.equations[0], &data, 1);
+        assert!(res.success, "{}", res.exception);
+        let pixel = data::Point {
+            x : data.size.x as f64 / data.pixel_resolution().x as f64,
+            y : data.size.y as f64 / data.pixel_resolution().y as f64
+        };
+        for curve in res.curves.iter() {
+            for pair in curve.windows(2) {
+                let dx = (pair[1].x - pair[0].x) / pixel.x;
+                let dy = (pair[1].y - pair[0].y) / pixel.y;
+                assert!((dx * dx + dy * dy).sqrt() <= 2.0, "Gap from {:?} to {:?}.", pair[0], pair[1]);
+            }
+        }
+        return res.curves;
+    }
+
+    // Samples either side of a vertical tangent are further apart than any fixed jump allowed, at low resolutions.
+    #[test]
+    fn vertical_tangents_stay_connected() {
+        let curves = trace_connected("#frame(-10, -10, 20, 20)\ny + sin(y) = x\n");
+        assert_eq!(curves.len(), 1);
+        let curves = trace_connected("#frame(-10, -10, 20, 20)\ny = root(3, x)\n");
+        assert_eq!(curves.len(), 1);
+    }
+
+    #[test]
+    fn folds_close_shapes() {
+        let curves = trace_connected("#frame(-10, -10, 20, 20)\nx * x + y * y = 16\n");
+        assert_eq!(curves.len(), 1);
+        assert_eq!(curves[0][0], curves[0][curves[0].len() - 1]);
+    }
+
+    #[test]
+    fn separate_curves_stay_apart() {
+        let curves = trace_connected("#frame(-10, -10, 20, 20)\n#resolution(100, 100)\n(x * x + y * y - 16) * (x * x + y * y - 9) = 0\n");
+        assert_eq!(curves.len(), 2);
+        for curve in curves.iter() {
+            assert_eq!(curve[0], curve[curve.len() - 1]);
+        }
+        let curves = trace_connected("#frame(-10, -10, 20, 20)\nx * y = 1\n");
+        assert_eq!(curves.len(), 2);
+    }
+
+    // Turn the roots typed in a script into `±` roots, as made when solving for a variable.
+    fn plus_minus(node : &mut nodes::Node) {
+        match &mut node.base {
+            nodes::NodeBase::EqualsExpression {left, right} | nodes::NodeBase::AdditionOperation {left, right} => {
+                plus_minus(left);
+                plus_minus(right);
+            },
+            nodes::NodeBase::FunctionRoot {user_typed, ..} => *user_typed = false,
+            _                                              => ()
+        }
+    }
+
+    // A repeated `±` root takes the same sign every time, so there are no mixed branches which would give zero.
+    #[test]
+    fn repeated_roots_share_a_sign() {
+        let mut data = run::interpret_text("#frame(-10, -10, 20, 20)\ny = root(2, x) + root(2, x)\n");
+        plus_minus(&mut data.equations[0]);
+
+        let program    = compiler::compile(&data.equations[0]).program;
+        let mut values = program.values(4.0, 0.0, &mut vec![]).values;
+        values.sort_by(f64::total_cmp);
+        assert_eq!(program.branches(), 2);
+        assert_eq!(values, vec![-4.0, 4.0]);
+        let root = data::MultipleValues::new_single(4.0).root(data::MultipleValues::new_single(2.0), false, data.equations[0].key());
+        assert_eq!((root.clone() + root).values, vec![4.0, -4.0]);
+
+        let res = trace(&data.equations[0], &data, 1);
+        assert!(res.success, "{}", res.exception);
+        assert_eq!(res.curves.len(), 2);
+        let signs = res.curves.iter().map(|curve| curve.iter().map(|point| point.y.signum()).sum::<f64>().signum()).collect::<Vec<f64>>();
+        assert!(signs.contains(&1.0) && signs.contains(&-1.0), "{:?}", signs);
+        for curve in res.curves.iter() {
+            for point in curve.iter() {
+                assert!((point.y.abs() - 2.0 * point.x.max(0.0).sqrt()).abs() <= 1e-6, "{:?} is off both branches.", point);
+                assert!(point.y == 0.0 || point.y.signum() == curve[curve.len() - 1].y.signum(), "{:?} changes branch.", point);
+            }
+        }
+    }
+
+    // Trace the only equation of a script, checking no segment of a curve crosses a pole, which lies between `side` values of a different sign.
+    fn no_segment_crosses(script : &str, side : fn(f64) -> f64) {
+        let data = run::interpret_text(script);
+        let res  = trace(&data.equations[0], &data, 1);
+        assert!(res.success, "{}", res.exception);
+        assert!(res.curves.len() > 1);
+        for curve in res.curves.iter() {
+            for pair in curve.windows(2) {
+                assert!(side(pair[0].x) * side(pair[1].x) > 0.0, "Segment from {:?} to {:?} crosses a pole.", pair[0], pair[1]);
+            }
+        }
+    }
+
+    #[test]
+    fn curves_break_at_poles() {
+        no_segment_crosses("#frame(-10, -10, 20, 20)\ny = tan(x)\n", f64::cos);
+        no_segment_crosses("#frame(-10, -10, 20, 20)\ny = 1 / x\n", |x| x);
+    }
+
+    #[test]
+    fn division_by_zero_level() {
+        let data = run::interpret_text("#division_by_zero(\"error\")\n#frame(-10, -10, 20, 20)\ny = 1 / x\n");
+        let res  = paths::extract(&data, 1);
+        assert!(! res.success);
+        assert!(matches!(res.exception.base, exceptions::RendererExceptionBase::DivisionByZeroException), "{}", res.exception);
+
+        let data = run::interpret_text("#division_by_zero(\"warning\")\n#frame(-10, -10, 20, 20)\ny = 1 / x\n");
+        assert!(paths::extract(&data, 1).success);
+        let data = run::interpret_text("#division_by_zero(\"error\")\n#frame(-10, -10, 20, 20)\ny = 1 / (x * x + 1)\n");
+        assert!(paths::extract(&data, 1).success);
+    }
+}
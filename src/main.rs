@@ -1,8 +1,75 @@
+// The codebase favours explicit returns, field names, `&'static str` constants, single arm matches,
+// formatted log messages, late initialisation, `...Exception` variants and `len()` comparisons for readability.
+#![allow(
+    clippy::needless_return,
+    clippy::redundant_field_names,
+    clippy::redundant_static_lifetimes,
+    clippy::single_match,
+    clippy::collapsible_match,
+    clippy::useless_format,
+    clippy::needless_late_init,
+    clippy::enum_variant_names,
+    clippy::len_zero
+)]
+
 mod core;
 
 
 
-// Basic run on specific file.
+use std::env;
+use std::process::exit;
+
+
+
+// Run on the file given on the command line.
+// Usage: lrinser [file] [--threads count] [--export file] [--machine name] [--benchmark]
 fn main() {
-    core::run::run("test_syntax.lrn");
+    let mut filename  = "test_syntax.lrn".to_string();
+    let mut options   = core::run::Options {
+        threads : 0,
+        export  : None,
+        machine : None
+    };
+    let mut benchmark = false;
+    let mut args      = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--benchmark" {
+            benchmark = true;
+        } else if arg == "--threads" {
+            options.threads = match args.next().map(|count| count.parse::<usize>()) {
+                Some(Ok(count)) => count,
+                _               => {
+                    println!("Expected a thread count after `--threads`.");
+                    exit(1);
+                }
+            };
+        } else if arg == "--export" {
+            options.export = match args.next() {
+                Some(export) => Some(export),
+                None         => {
+                    println!("Expected a filename after `--export`.");
+                    exit(1);
+                }
+            };
+        } else if arg == "--machine" {
+            options.machine = match args.next() {
+                Some(machine) => Some(machine),
+                None          => {
+                    println!("Expected a machine name after `--machine`.");
+                    exit(1);
+                }
+            };
+        } else {
+            filename = arg;
+        }
+    }
+
+    if benchmark {
+        // Differences between the evaluators fail the run, so benchmarks can be used as a check.
+        if ! core::benchmark::benchmark(&filename) {
+            exit(1);
+        }
+    } else {
+        core::run::run(&filename, &options);
+    }
 }
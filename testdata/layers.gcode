@@ -0,0 +1,300 @@
+G21
+G90
+M5
+; Layer `Fill`, engrave.
+; Raster at 40 by 40 pixels, power 40%, speed 3000 mm/min, 1 passes.
+; Pass 1 of 1.
+G0 X-2 Y19.75
+M4 S0
+G1 X0 F3000
+G1 X20
+G1 X22
+M5
+G0 X22 Y19.25
+M4 S0
+G1 X20 F3000
+G1 X0
+G1 X-2
+M5
+G0 X-2 Y18.75
+M4 S0
+G1 X0 F3000
+G1 X20
+G1 X22
+M5
+G0 X22 Y18.25
+M4 S0
+G1 X20 F3000
+G1 X0
+G1 X-2
+M5
+G0 X-2 Y17.75
+M4 S0
+G1 X0 F3000
+G1 X20
+G1 X22
+M5
+G0 X22 Y17.25
+M4 S0
+G1 X20 F3000
+G1 X0
+G1 X-2
+M5
+G0 X-2 Y16.75
+M4 S0
+G1 X0 F3000
+G1 X20
+G1 X22
+M5
+G0 X22 Y16.25
+M4 S0
+G1 X20 F3000
+G1 X0
+G1 X-2
+M5
+G0 X-2 Y15.75
+M4 S0
+G1 X0 F3000
+G1 X20
+G1 X22
+M5
+G0 X22 Y15.25
+M4 S0
+G1 X20 F3000
+G1 X0
+G1 X-2
+M5
+G0 X-2 Y14.75
+M4 S0
+G1 X0 F3000
+G1 X20
+G1 X22
+M5
+G0 X22 Y14.25
+M4 S0
+G1 X20 F3000
+G1 X0
+G1 X-2
+M5
+G0 X-2 Y13.75
+M4 S0
+G1 X0 F3000
+G1 X20
+G1 X22
+M5
+G0 X22 Y13.25
+M4 S0
+G1 X20 F3000
+G1 X0
+G1 X-2
+M5
+G0 X-2 Y12.75
+M4 S0
+G1 X0 F3000
+G1 X20
+G1 X22
+M5
+G0 X22 Y12.25
+M4 S0
+G1 X20 F3000
+G1 X0
+G1 X-2
+M5
+G0 X-2 Y11.75
+M4 S0
+G1 X0 F3000
+G1 X7
+G1 X13 S400
+G1 X20 S0
+G1 X22
+M5
+G0 X22 Y11.25
+M4 S0
+G1 X20 F3000
+G1 X13.5
+G1 X12.5 S400
+G1 X7.5 S0
+G1 X6.5 S400
+G1 X0 S0
+G1 X-2
+M5
+G0 X-2 Y10.75
+M4 S0
+G1 X0 F3000
+G1 X6
+G1 X7 S400
+G1 X13 S0
+G1 X14 S400
+G1 X20 S0
+G1 X22
+M5
+G0 X22 Y10.25
+M4 S0
+G1 X20 F3000
+G1 X14.5
+G1 X13.5 S400
+G1 X6.5 S0
+G1 X5.5 S400
+G1 X0 S0
+G1 X-2
+M5
+G0 X-2 Y9.75
+M4 S0
+G1 X0 F3000
+G1 X5.5
+G1 X6.5 S400
+G1 X13.5 S0
+G1 X14.5 S400
+G1 X20 S0
+G1 X22
+M5
+G0 X22 Y9.25
+M4 S0
+G1 X20 F3000
+G1 X14
+G1 X13 S400
+G1 X7 S0
+G1 X6 S400
+G1 X0 S0
+G1 X-2
+M5
+G0 X-2 Y8.75
+M4 S0
+G1 X0 F3000
+G1 X6.5
+G1 X7.5 S400
+G1 X12.5 S0
+G1 X13.5 S400
+G1 X20 S0
+G1 X22
+M5
+G0 X22 Y8.25
+M4 S0
+G1 X20 F3000
+G1 X13
+G1 X7 S400
+G1 X0 S0
+G1 X-2
+M5
+G0 X-2 Y7.75
+M4 S0
+G1 X0 F3000
+G1 X20
+G1 X22
+M5
+G0 X22 Y7.25
+M4 S0
+G1 X20 F3000
+G1 X0
+G1 X-2
+M5
+G0 X-2 Y6.75
+M4 S0
+G1 X0 F3000
+G1 X20
+G1 X22
+M5
+G0 X22 Y6.25
+M4 S0
+G1 X20 F3000
+G1 X0
+G1 X-2
+M5
+G0 X-2 Y5.75
+M4 S0
+G1 X0 F3000
+G1 X20
+G1 X22
+M5
+G0 X22 Y5.25
+M4 S0
+G1 X20 F3000
+G1 X0
+G1 X-2
+M5
+G0 X-2 Y4.75
+M4 S0
+G1 X0 F3000
+G1 X20
+G1 X22
+M5
+G0 X22 Y4.25
+M4 S0
+G1 X20 F3000
+G1 X0
+G1 X-2
+M5
+G0 X-2 Y3.75
+M4 S0
+G1 X0 F3000
+G1 X20
+G1 X22
+M5
+G0 X22 Y3.25
+M4 S0
+G1 X20 F3000
+G1 X0
+G1 X-2
+M5
+G0 X-2 Y2.75
+M4 S0
+G1 X0 F3000
+G1 X20
+G1 X22
+M5
+G0 X22 Y2.25
+M4 S0
+G1 X20 F3000
+G1 X0
+G1 X-2
+M5
+G0 X-2 Y1.75
+M4 S0
+G1 X0 F3000
+G1 X20
+G1 X22
+M5
+G0 X22 Y1.25
+M4 S0
+G1 X20 F3000
+G1 X0
+G1 X-2
+M5
+G0 X-2 Y0.75
+M4 S0
+G1 X0 F3000
+G1 X20
+G1 X22
+M5
+G0 X22 Y0.25
+M4 S0
+G1 X20 F3000
+G1 X0
+G1 X-2
+; Layer `Marks`, score.
+; Power 20%, speed 2000 mm/min, 1 passes.
+; Pass 1 of 1.
+; (y = (x / 2))
+M5
+G0 X0 Y5
+M4 S200
+G1 X20 Y15 F2000
+M5
+; Layer `Outline`, cut.
+; Power 90%, speed 300 mm/min, 2 passes.
+; Pass 1 of 2.
+; (((x * x) + (y * y)) = 64)
+G0 X17.5 Y12.784
+M4 S900
+G3 X17.803 Y11.763 I-7.5 J-2.784 F300
+G1 X17.5 Y12.784
+M5
+; Pass 2 of 2.
+; (((x * x) + (y * y)) = 64)
+G0 X17.5 Y12.784
+M4 S900
+G3 X17.803 Y11.763 I-7.5 J-2.784 F300
+G1 X17.5 Y12.784
+M5
+G0 X0 Y0
+M2